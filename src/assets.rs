@@ -2,7 +2,8 @@ use bevy::prelude::*;
 use bevy_aseprite_ultra::prelude::*;
 use bevy_asset_loader::prelude::*;
 use bevy_common_assets::ron::RonAssetPlugin;
-use bevy_kira_audio::{Audio, AudioControl, AudioSource};
+use bevy_kira_audio::AudioSource;
+use iyes_progress::ProgressPlugin;
 
 use crate::{
     AppState,
@@ -17,18 +18,22 @@ impl Plugin for AssetsPlugin {
             .add_plugins((
                 AsepriteUltraPlugin,
                 RonAssetPlugin::<Levels>::new(&["level.ron"]),
+                // Move on once everything tracked during loading is done.
+                ProgressPlugin::<AppState>::new()
+                    .with_state_transition(AppState::Loading, AppState::StartMenu),
             ))
             .add_loading_state(
                 LoadingState::new(AppState::Loading)
-                    .continue_to_state(AppState::StartMenu)
+                    .on_failure_continue_to_state(AppState::LoadingError)
                     .load_collection::<GameAssets>()
                     .load_collection::<SfxAssets>()
+                    .load_collection::<MusicAssets>()
             )
-            .add_systems(OnExit(AppState::Loading), assets_loaded);
+            .add_systems(OnTransition { exited: AppState::Loading, entered: AppState::StartMenu }, assets_loaded);
     }
 }
 
-#[derive(Resource, AssetCollection)]
+#[derive(Default, Resource, AssetCollection)]
 pub struct GameAssets {
     #[asset(path = "fonts/Kenney Pixel.ttf")]
     pub font: Handle<Font>,
@@ -69,7 +74,7 @@ pub struct GameAssets {
     pub levels: Handle<Levels>,
 }
 
-#[derive(Resource, AssetCollection)]
+#[derive(Default, Resource, AssetCollection)]
 pub struct SfxAssets {
     // Dog sounds.
     #[asset(path = "sounds/dog_yip_1.wav")]
@@ -77,13 +82,13 @@ pub struct SfxAssets {
     #[asset(path = "sounds/dog_woof_1.wav")]
     pub dog_woof: Handle<AudioSource>,
 
-    // Cat meows.
-    #[asset(path = "sounds/basic_cat_meow_1.wav")]
-    pub basic_cat_meow: Handle<AudioSource>,
-    #[asset(path = "sounds/kitten_meow_1.wav")]
-    pub kitten_meow: Handle<AudioSource>,
-    #[asset(path = "sounds/fat_cat_meow_1.wav")]
-    pub fat_cat_meow: Handle<AudioSource>,
+    // Cat meows. Add more paths to get more variety.
+    #[asset(paths("sounds/basic_cat_meow_1.wav"), collection(typed))]
+    pub basic_cat_meow: Vec<Handle<AudioSource>>,
+    #[asset(paths("sounds/kitten_meow_1.wav"), collection(typed))]
+    pub kitten_meow: Vec<Handle<AudioSource>>,
+    #[asset(paths("sounds/fat_cat_meow_1.wav"), collection(typed))]
+    pub fat_cat_meow: Vec<Handle<AudioSource>>,
 
     #[asset(paths(
         "sounds/angry_cat_meow_1.ogg",
@@ -92,27 +97,35 @@ pub struct SfxAssets {
         "sounds/angry_cat_meow_4.ogg"
     ), collection(typed))]
     pub angry_cat: Vec<Handle<AudioSource>>,
+}
 
+/// Background music for each part of the game. Point these at different files to change the music.
+#[derive(Resource, AssetCollection)]
+pub struct MusicAssets {
+    #[asset(path = "sounds/trolling_doggo.ogg")]
+    pub menu: Handle<AudioSource>,
     #[asset(path = "sounds/trolling_doggo.ogg")]
-    pub bgm: Handle<AudioSource>,
+    pub level: Handle<AudioSource>,
+    #[asset(path = "sounds/trolling_doggo.ogg")]
+    pub victory: Handle<AudioSource>,
+}
+
+impl MusicAssets {
+    /// Seconds into each track to loop back to, so intros only play once. Update these along with the paths above.
+    pub const MENU_LOOP_START: f64 = 24.0;
+    pub const LEVEL_LOOP_START: f64 = 24.0;
+    pub const VICTORY_LOOP_START: f64 = 24.0;
 }
 
 fn assets_loaded(
-    mut level_assets: ResMut<Assets<Levels>>,
-    audio: Res<Audio>,
-    mut assets: ResMut<GameAssets>,
+    level_assets: Res<Assets<Levels>>,
+    assets: Res<GameAssets>,
     mut levels: ResMut<Levels>,
-    sfx: Res<SfxAssets>,
 ) {
     debug!("Loaded assets!");
 
-    // Move loaded Levels to a resource and remove the asset/clear the handle.
-    if let Some(loaded_levels) = level_assets.remove(&assets.levels) {
-        *levels = loaded_levels;
-        assets.levels = Handle::default();
+    // Copy loaded Levels to a resource. Keep the asset around so it can be hot-reloaded.
+    if let Some(loaded_levels) = level_assets.get(&assets.levels) {
+        *levels = loaded_levels.clone();
     }
-
-    audio.play(sfx.bgm.clone())
-        .loop_from(24.0)
-        .with_volume(0.2);
 }
@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::ecs::system::SystemParam;
+use bevy_kira_audio::{AudioApp, AudioChannel, AudioControl, AudioInstance, AudioSource, AudioTween, PlaybackState};
+use fastrand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AppState, WORLD_SIZE,
+    assets::MusicAssets,
+    cats::Cat,
+    dog::Dog,
+    game::GameState,
+};
+
+/// Volume of the music relative to sound effects, before the player's settings are applied.
+const MUSIC_MIX: f32 = 0.2;
+/// How much to turn the music down while a cat is cannonballing.
+const MUSIC_DUCK: f32 = 0.4;
+const DUCK_FADE_TIME: f32 = 0.3;
+const MUSIC_FADE_TIME: f32 = 1.0;
+
+/// Don't pan all the way so sounds at the edges can still be heard in both ears.
+const MAX_PANNING: f32 = 0.8;
+/// Sounds get quieter with distance until they reach the minimum volume at this distance.
+const FALLOFF_DISTANCE: f32 = 300.0;
+const MIN_DISTANCE_VOLUME: f32 = 0.3;
+/// Most meows that can play at once.
+const MAX_MEOW_VOICES: usize = 4;
+const VOICE_STEAL_FADE_TIME: f32 = 0.05;
+/// Kira treats this many decibels and below as silence.
+const SILENCE_DB: f32 = -60.0;
+
+/// Channel for background music.
+#[derive(Resource)]
+pub struct MusicChannel;
+
+/// Channel for sound effects in the game world.
+#[derive(Resource)]
+pub struct SfxChannel;
+
+/// Channel for menu sounds, which keep playing at full volume while the music is ducked.
+#[derive(Resource)]
+pub struct UiChannel;
+
+#[derive(Clone, Debug, Deserialize, Serialize, Resource)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub master: f32,
+    pub music: f32,
+    pub sfx: f32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            master: 1.0,
+            music: 1.0,
+            sfx: 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MusicTrack {
+    Menu,
+    Level,
+    Victory,
+}
+
+impl MusicTrack {
+    fn source(&self, music_assets: &MusicAssets) -> Handle<AudioSource> {
+        match self {
+            MusicTrack::Menu => music_assets.menu.clone(),
+            MusicTrack::Level => music_assets.level.clone(),
+            MusicTrack::Victory => music_assets.victory.clone(),
+        }
+    }
+
+    /// Where the track loops back to once it ends.
+    fn loop_start(&self) -> f64 {
+        match self {
+            MusicTrack::Menu => MusicAssets::MENU_LOOP_START,
+            MusicTrack::Level => MusicAssets::LEVEL_LOOP_START,
+            MusicTrack::Victory => MusicAssets::VICTORY_LOOP_START,
+        }
+    }
+}
+
+/// The track that's playing, if any.
+#[derive(Default, Resource)]
+struct CurrentMusic(Option<MusicTrack>);
+
+/// Whether the music is turned down so cannonballing cats can be heard.
+#[derive(Default, PartialEq, Resource)]
+struct MusicDucked(bool);
+
+/// Where sounds are heard from. Follows the dog in single player, otherwise stays in the middle of the screen.
+#[derive(Default, PartialEq, Resource)]
+pub struct Listener(Vec2);
+
+impl Listener {
+    /// Kira pans from -1 (left) to 1 (right).
+    fn panning(&self, pos: Vec2) -> f32 {
+        let offset = (pos.x - self.0.x) / (WORLD_SIZE.x as f32 / 2.0);
+        offset.clamp(-1.0, 1.0) * MAX_PANNING
+    }
+
+    fn volume(&self, pos: Vec2) -> f32 {
+        let distance = self.0.distance(pos);
+        (1.0 - distance / FALLOFF_DISTANCE).max(MIN_DISTANCE_VOLUME)
+    }
+}
+
+/// Meows that are currently playing.
+#[derive(Default, Resource)]
+struct MeowVoices(Vec<Handle<AudioInstance>>);
+
+/// The variant last picked from each list of sounds, keyed by the list's first sound.
+#[derive(Default, Resource)]
+struct LastVariants(HashMap<AssetId<AudioSource>, usize>);
+
+/// Plays sound effects panned and attenuated by where they come from.
+#[derive(SystemParam)]
+pub struct SpatialAudio<'w> {
+    sfx: Res<'w, AudioChannel<SfxChannel>>,
+    listener: Res<'w, Listener>,
+    meow_voices: ResMut<'w, MeowVoices>,
+    last_variants: ResMut<'w, LastVariants>,
+    instances: ResMut<'w, Assets<AudioInstance>>,
+}
+
+impl SpatialAudio<'_> {
+    pub fn play(&self, source: Handle<AudioSource>, pos: Vec2) {
+        self.play_with(source, pos, 1.0, 1.0);
+    }
+
+    fn play_with(&self, source: Handle<AudioSource>, pos: Vec2, volume: f32, pitch: f32) -> Handle<AudioInstance> {
+        self.sfx.play(source)
+            .with_volume(to_decibels(volume * self.listener.volume(pos)))
+            .with_panning(self.listener.panning(pos))
+            .with_playback_rate(pitch as f64)
+            .handle()
+    }
+
+    /// Pick a random variant, avoiding the one picked last time from the same list.
+    fn pick_variant(&mut self, rng: &mut Rng, variants: &[Handle<AudioSource>]) -> Option<Handle<AudioSource>> {
+        let first = variants.first()?;
+        let last = self.last_variants.0.get(&first.id()).copied();
+        let mut index = rng.usize(..variants.len());
+        if variants.len() > 1 && Some(index) == last {
+            // Shift to any of the other variants.
+            index = (index + rng.usize(1..variants.len())) % variants.len();
+        }
+        self.last_variants.0.insert(first.id(), index);
+        Some(variants[index].clone())
+    }
+
+    /// Play one of the meow variants unless too many cats are meowing already.
+    /// Urgent meows cut off the oldest meow instead of being dropped.
+    pub fn play_meow(&mut self, rng: &mut Rng, variants: &[Handle<AudioSource>], pos: Vec2, volume: f32, pitch: f32, urgent: bool) {
+        let instances = &self.instances;
+        // Instances only show up once kira gets to the play command, so keep voices that haven't started yet.
+        self.meow_voices.0.retain(|voice| {
+            instances.get(voice).is_none_or(|instance| !matches!(instance.state(), PlaybackState::Stopped))
+        });
+
+        if self.meow_voices.0.len() >= MAX_MEOW_VOICES {
+            if !urgent {
+                return;
+            }
+            let oldest = self.meow_voices.0.remove(0);
+            if let Some(instance) = self.instances.get_mut(&oldest) {
+                instance.stop(AudioTween::linear(Duration::from_secs_f32(VOICE_STEAL_FADE_TIME)));
+            }
+        }
+
+        let Some(source) = self.pick_variant(rng, variants) else {
+            return;
+        };
+        let voice = self.play_with(source, pos, volume, pitch);
+        self.meow_voices.0.push(voice);
+    }
+}
+
+pub struct AudioPlugin {
+    saved_audio_settings: AudioSettings,
+}
+
+impl AudioPlugin {
+    pub fn new(saved_audio_settings: AudioSettings) -> Self {
+        Self {
+            saved_audio_settings,
+        }
+    }
+}
+
+impl Plugin for AudioPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_audio_channel::<MusicChannel>()
+            .add_audio_channel::<SfxChannel>()
+            .add_audio_channel::<UiChannel>()
+            .insert_resource(self.saved_audio_settings.clone())
+            .init_resource::<CurrentMusic>()
+            .init_resource::<MusicDucked>()
+            .init_resource::<Listener>()
+            .init_resource::<MeowVoices>()
+            .init_resource::<LastVariants>()
+            .add_systems(Update, (
+                update_listener,
+                change_music.run_if(state_changed::<AppState>.or(state_changed::<GameState>)),
+                duck_music,
+                apply_volumes.run_if(resource_changed::<AudioSettings>.or(resource_changed::<MusicDucked>)),
+            ).chain());
+    }
+}
+
+fn change_music(
+    app_state: Res<State<AppState>>,
+    game_state: Res<State<GameState>>,
+    music_assets: Option<Res<MusicAssets>>,
+    music: Res<AudioChannel<MusicChannel>>,
+    mut current_music: ResMut<CurrentMusic>,
+) {
+    let Some(music_assets) = music_assets else {
+        return;
+    };
+    let track = match (app_state.get(), game_state.get()) {
+        (AppState::Loading, _) => return,
+        (AppState::Playing, GameState::Victory) => MusicTrack::Victory,
+        (AppState::Playing, _) => MusicTrack::Level,
+        _ => MusicTrack::Menu,
+    };
+    if current_music.0 == Some(track) {
+        return;
+    }
+
+    // Fade out whatever was playing while the new track fades in.
+    let fade = Duration::from_secs_f32(MUSIC_FADE_TIME);
+    music.stop().fade_out(AudioTween::linear(fade));
+    music.play(track.source(&music_assets))
+        .loop_from(track.loop_start())
+        .fade_in(AudioTween::linear(fade));
+    current_music.0 = Some(track);
+}
+
+fn duck_music(
+    cat_q: Query<&Cat>,
+    mut ducked: ResMut<MusicDucked>,
+) {
+    let cannonballing = cat_q.iter().any(|cat| cat.state.is_cannonballing());
+    ducked.set_if_neq(MusicDucked(cannonballing));
+}
+
+fn update_listener(
+    dog_q: Query<&GlobalTransform, With<Dog>>,
+    mut listener: ResMut<Listener>,
+) {
+    let pos = match dog_q.single() {
+        Ok(transform) => transform.translation().truncate(),
+        Err(_) => Vec2::ZERO,
+    };
+    listener.set_if_neq(Listener(pos));
+}
+
+fn apply_volumes(
+    settings: Res<AudioSettings>,
+    ducked: Res<MusicDucked>,
+    music: Res<AudioChannel<MusicChannel>>,
+    sfx: Res<AudioChannel<SfxChannel>>,
+    ui: Res<AudioChannel<UiChannel>>,
+) {
+    let duck = if ducked.0 { MUSIC_DUCK } else { 1.0 };
+    music.set_volume(to_decibels(settings.master * settings.music * MUSIC_MIX * duck))
+        .fade_in(AudioTween::linear(Duration::from_secs_f32(DUCK_FADE_TIME)));
+    sfx.set_volume(to_decibels(settings.master * settings.sfx));
+    ui.set_volume(to_decibels(settings.master * settings.sfx));
+}
+
+/// Convert a linear volume, like the settings sliders and distance falloff use, to the decibels Kira takes.
+fn to_decibels(volume: f32) -> f32 {
+    if volume <= 0.0 {
+        SILENCE_DB
+    } else {
+        (20.0 * volume.log10()).max(SILENCE_DB)
+    }
+}
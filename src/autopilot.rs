@@ -0,0 +1,232 @@
+use bevy::prelude::*;
+
+use crate::{
+    WORLD_SIZE,
+    cats::{Annoyance, Cat, CatState, FLEE_RANGE},
+    dog::{Dog, BARK_RADIUS},
+    game::{CatBox, GameSet},
+    input::{InputDevice, PlayerInput},
+    physics::Velocity,
+    replay,
+};
+
+/// How far behind a cat to stand, close enough that it runs away from the dog.
+const HERD_DISTANCE: f32 = FLEE_RANGE - 25.0;
+/// How far to stay from a cat while going around it, so it doesn't get chased the wrong way.
+const CIRCLE_DISTANCE: f32 = FLEE_RANGE + 10.0;
+/// How lined up with the cat and the pen the dog has to be to count as behind the cat.
+const BEHIND_ALIGNMENT: f32 = 0.5;
+/// Start slowing down this close to where the dog wants to be.
+const ARRIVE_DISTANCE: f32 = 20.0;
+/// Keep away from the walls so there's room to get behind cats.
+const WALL_MARGIN: f32 = 10.0;
+
+/// Leave a cat alone once it gets this annoyed, until it calms back down.
+const BACK_OFF_ANNOYANCE: f32 = 0.7;
+const CALM_ANNOYANCE: f32 = 0.3;
+
+/// Only bark when lined up this well, far enough from the pen that it's worth it, and the cat is calm.
+const BARK_ALIGNMENT: f32 = 0.9;
+const BARK_MIN_PEN_DISTANCE: f32 = 80.0;
+const BARK_MAX_ANNOYANCE: f32 = 0.4;
+
+/// Get out of the way of cats that are about to cannonball within this range.
+const DODGE_RANGE: f32 = 200.0;
+/// How close to a cannonballing cat's path counts as in the way.
+const DODGE_WIDTH: f32 = 35.0;
+
+type CatQuery<'w, 's> = Query<'w, 's, (Entity, &'static Cat, &'static Annoyance, &'static Transform, &'static Velocity)>;
+
+pub struct AutopilotPlugin {
+    all_dogs: bool,
+}
+
+impl AutopilotPlugin {
+    /// Drive every dog with the autopilot, not just during demos.
+    pub fn new(all_dogs: bool) -> Self {
+        Self {
+            all_dogs,
+        }
+    }
+}
+
+impl Plugin for AutopilotPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(AutopilotSettings {
+                all_dogs: self.all_dogs,
+            })
+            .add_observer(add_autopilot)
+            .add_systems(FixedUpdate, drive_autopilot.in_set(GameSet::Input).before(replay::record_inputs));
+    }
+}
+
+#[derive(Resource)]
+pub struct AutopilotSettings {
+    /// Drive every dog that spawns, e.g. to measure clear times with `--headless --autopilot`.
+    pub all_dogs: bool,
+}
+
+/// A demo is playing on its own from the title screen. Dogs are driven by the autopilot and nothing gets saved.
+#[derive(Resource)]
+pub struct Demo {
+    /// Who had joined before the demo, to put back once it ends.
+    pub saved_players: Vec<InputDevice>,
+}
+
+/// Writes `PlayerInput` for a dog by herding cats into the pen one at a time.
+#[derive(Default, Component)]
+pub struct Autopilot {
+    target: Option<Entity>,
+    backing_off: bool,
+}
+
+fn add_autopilot(
+    add: On<Add, Dog>,
+    mut commands: Commands,
+    settings: Res<AutopilotSettings>,
+    demo: Option<Res<Demo>>,
+) {
+    if settings.all_dogs || demo.is_some() {
+        commands.entity(add.entity).insert(Autopilot::default());
+    }
+}
+
+fn drive_autopilot(
+    mut dog_q: Query<(&mut Autopilot, &mut PlayerInput, &Dog, &Transform)>,
+    cat_q: CatQuery,
+    cat_box_q: Query<&Transform, With<CatBox>>,
+) {
+    let Ok(cat_box_transform) = cat_box_q.single() else {
+        return;
+    };
+    let pen_pos = cat_box_transform.translation.truncate();
+
+    // Cats that dogs are already herding, so multiple dogs spread out.
+    let mut claimed: Vec<Entity> = dog_q.iter()
+        .filter_map(|(autopilot, ..)| autopilot.target)
+        .collect();
+    for (mut autopilot, mut input, dog, transform) in dog_q.iter_mut() {
+        let pos = transform.translation.truncate();
+        input.bark = false;
+
+        if let Some(dodge) = dodge_cannonballs(pos, &cat_q) {
+            input.movement = dodge;
+            continue;
+        }
+
+        // Keep herding the same cat until it's in the pen.
+        let target = autopilot.target
+            .and_then(|entity| cat_q.get(entity).ok())
+            .filter(|(_, cat, ..)| cat.state != CatState::InPen);
+        let target = match target {
+            Some(target) => target,
+            None => {
+                autopilot.backing_off = false;
+                autopilot.target = pick_target(pos, pen_pos, &cat_q, &claimed);
+                let Some(target) = autopilot.target.and_then(|entity| cat_q.get(entity).ok()) else {
+                    input.movement = Vec2::ZERO;
+                    continue;
+                };
+                claimed.push(target.0);
+                target
+            }
+        };
+        let (_, cat, annoyance, cat_transform, _) = target;
+        let cat_pos = cat_transform.translation.truncate();
+
+        // Give annoyed cats space to calm down.
+        if annoyance.level() >= BACK_OFF_ANNOYANCE {
+            autopilot.backing_off = true;
+        } else if annoyance.level() <= CALM_ANNOYANCE {
+            autopilot.backing_off = false;
+        }
+        if autopilot.backing_off {
+            let from_cat = pos - cat_pos;
+            input.movement = if from_cat.length() < CIRCLE_DISTANCE {
+                from_cat.normalize_or(Vec2::X)
+            } else {
+                Vec2::ZERO
+            };
+            continue;
+        }
+
+        let to_pen = (pen_pos - cat_pos).normalize_or_zero();
+        let from_cat = pos - cat_pos;
+        let behind = from_cat.normalize_or_zero().dot(-to_pen);
+        input.movement = if from_cat.length() < CIRCLE_DISTANCE && behind < BEHIND_ALIGNMENT {
+            // Go around the cat on the side closer to behind it, instead of chasing it away from the pen.
+            let out = from_cat.normalize_or(-to_pen);
+            let mut around = out.perp();
+            if around.dot(-to_pen) < 0.0 {
+                around = -around;
+            }
+            let push_out = out * (CIRCLE_DISTANCE - from_cat.length()) / CIRCLE_DISTANCE;
+            (around + push_out).normalize_or_zero()
+        } else {
+            let bounds = WORLD_SIZE.as_vec2() / 2.0 - Vec2::splat(WALL_MARGIN);
+            let herd_pos = (cat_pos - to_pen * HERD_DISTANCE).clamp(-bounds, bounds);
+            ((herd_pos - pos) / ARRIVE_DISTANCE).clamp_length_max(1.0)
+        };
+
+        // Bark to send the cat running on long pushes.
+        input.bark = dog.can_bark()
+            && matches!(cat.state, CatState::Wander { .. } | CatState::Flee)
+            && behind >= BARK_ALIGNMENT
+            && from_cat.length() < BARK_RADIUS
+            && cat_pos.distance(pen_pos) > BARK_MIN_PEN_DISTANCE
+            && annoyance.level() < BARK_MAX_ANNOYANCE;
+    }
+}
+
+/// Pick the cat that's quickest to get behind, preferring ones no other dog is herding.
+fn pick_target(
+    pos: Vec2,
+    pen_pos: Vec2,
+    cat_q: &CatQuery,
+    claimed: &[Entity],
+) -> Option<Entity> {
+    cat_q.iter()
+        .filter(|(_, cat, ..)| cat.state != CatState::InPen)
+        .map(|(entity, _, _, transform, _)| {
+            let cat_pos = transform.translation.truncate();
+            let herd_pos = cat_pos - (pen_pos - cat_pos).normalize_or_zero() * HERD_DISTANCE;
+            (entity, claimed.contains(&entity), pos.distance_squared(herd_pos))
+        })
+        .min_by(|(_, a_claimed, a), (_, b_claimed, b)| a_claimed.cmp(b_claimed).then(a.total_cmp(b)))
+        .map(|(entity, ..)| entity)
+}
+
+/// Which way to move to get out of the way of cats that are cannonballing or about to, if any.
+fn dodge_cannonballs(
+    pos: Vec2,
+    cat_q: &CatQuery,
+) -> Option<Vec2> {
+    cat_q.iter().find_map(|(_, cat, _, transform, velocity)| {
+        let from_cat = pos - transform.translation.truncate();
+        let (path, in_the_way) = match &cat.state {
+            // It'll launch straight at the dog, so keep moving across its aim.
+            CatState::Jittering { .. } => (from_cat.normalize_or_zero(), from_cat.length() < DODGE_RANGE),
+            CatState::Cannonballing { .. } => {
+                let path = velocity.normalize_or_zero();
+                let ahead = from_cat.dot(path) > 0.0;
+                (path, ahead && from_cat.perp_dot(path).abs() < DODGE_WIDTH)
+            }
+            _ => return None,
+        };
+        if !in_the_way {
+            return None;
+        }
+        // Step off the path on the side we're already on, or towards the middle of the room.
+        let side = path.perp();
+        let away = side.dot(from_cat);
+        let dodge = if away.abs() > 1.0 {
+            side * away.signum()
+        } else if side.dot(-pos) >= 0.0 {
+            side
+        } else {
+            -side
+        };
+        Some(dodge)
+    })
+}
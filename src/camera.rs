@@ -1,9 +1,10 @@
 use bevy::prelude::*;
 use bevy::camera::{ScalingMode, Viewport};
-use bevy::window::{PrimaryWindow, WindowResized};
+use bevy::window::PrimaryWindow;
 
 use crate::{
     AppState, SCREEN_SIZE, WORLD_SIZE,
+    window::update_window_state,
 };
 
 pub struct CameraPlugin;
@@ -11,9 +12,10 @@ pub struct CameraPlugin;
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_systems(OnExit(AppState::Loading), spawn_camera)
+            .add_systems(Startup, spawn_camera)
             .add_systems(OnEnter(AppState::Playing), scale_camera)
-            .add_systems(Update, handle_window_resize);
+            .add_systems(OnExit(AppState::Playing), unscale_camera)
+            .add_systems(PostUpdate, update_viewport.after(update_window_state));
     }
 }
 
@@ -53,24 +55,32 @@ fn scale_camera(
     }
 }
 
-fn handle_window_resize(
-    mut resize_messages: MessageReader<WindowResized>,
+fn unscale_camera(
+    mut camera_q: Query<&mut Projection, With<Camera>>,
+) {
+    for mut projection in camera_q.iter_mut() {
+        if let Projection::Orthographic(projection) = projection.as_mut() {
+            projection.scaling_mode = ScalingMode::Fixed {
+                width: SCREEN_SIZE.x as f32,
+                height: SCREEN_SIZE.y as f32,
+            };
+        }
+    }
+}
+
+fn update_viewport(
     mut camera_q: Query<&mut Camera>,
-    window_q: Query<(Entity, &Window), With<PrimaryWindow>>,
+    window_q: Query<&Window, (With<PrimaryWindow>, Changed<Window>)>,
 ) {
-    let Ok((window_entity, window)) = window_q.single() else {
+    // Follow changes to the window right away instead of waiting for it to report being resized.
+    let Ok(window) = window_q.single() else {
         return;
     };
     let Ok(mut camera) = camera_q.single_mut() else {
         return;
     };
 
-    for e in resize_messages.read() {
-        if e.window != window_entity {
-            continue;
-        }
-        camera.viewport = compute_viewport(window.physical_width(), window.physical_height());
-    }
+    camera.viewport = compute_viewport(window.physical_width(), window.physical_height());
 }
 
 fn compute_viewport(physical_width: u32, physical_height: u32) -> Option<Viewport> {
@@ -8,20 +8,26 @@ use avian2d::prelude::{Collider, CollisionLayers};
 use bevy::prelude::*;
 use bevy::sprite::Anchor;
 use bevy_aseprite_ultra::prelude::*;
-use bevy_kira_audio::{Audio, AudioControl};
+use fastrand::Rng;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    WORLD_SIZE, AppState,
-    assets::SfxAssets,
-    dog::Dog,
-    game::CatBox,
-    physics::{self, collider, GameLayer, MovementBounds, Velocity},
+    WORLD_SIZE,
+    audio::SpatialAudio,
+    assets::{GameAssets, SfxAssets},
+    dog::{Bark, Dog},
+    game::{CatBox, GameSet, GameState},
+    physics::{collider, GameLayer, MovementBounds, Obstacle, Solid, Velocity},
+    rng::GameRng,
 };
 
 pub const CAT_BOUNDS: f32 = 15.0;
 pub const CATBOX_BUFFER: f32 = 70.0;
 
-const FLEE_RANGE: f32 = 70.0;
+const CAT_BODY_HALF_SIZE: f32 = 10.0;
+const OBSTACLE_AVOID_RANGE: f32 = 25.0;
+
+pub const FLEE_RANGE: f32 = 70.0;
 const FLEE_BUFFER: f32 = 0.0;
 
 const JITTER_TIME: f32 = 1.0;
@@ -29,23 +35,39 @@ const JITTER_AMOUNT: f32 = 2.0;
 const CANNONBALL_TIME: f32 = 1.25;
 const CANNONBALL_SPEED: f32 = 240.0;
 
+const SCARED_TIME: f32 = 0.75;
+const SCARED_SPEED_BOOST: f32 = 1.6;
+const BARK_ANNOYANCE: f32 = 0.25;
+
+/// Wander steering was tuned at this many updates per second.
+const WANDER_TUNED_RATE: f32 = 60.0;
+const WANDER_ANGLE_JITTER: f32 = 18.0;
+
 const MEOW_RANGE: Range<f32> = 3.0..10.0;
+/// Playback rate range for cat voices, so no two cats sound quite the same.
+const VOICE_PITCH_RANGE: Range<f32> = 0.85..1.2;
+
+const CELEBRATE_HOP_HEIGHT: f32 = 4.0;
+const CELEBRATE_HOP_SPEED: f32 = 8.0;
 
 pub struct CatsPlugin;
 
 impl Plugin for CatsPlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_systems(Update, (
-                update_cats.before(physics::update_movement),
-                (cat_animation, cat_color).after(update_cats),
+            .add_systems(FixedUpdate, (
+                update_cats,
                 cat_meows,
-                init_cat_color,
-            ).run_if(in_state(AppState::Playing)));
+            ).in_set(GameSet::Logic))
+            .add_systems(Update, (
+                cat_animation,
+                cat_color,
+                celebrate_in_pen.after(cat_animation).run_if(in_state(GameState::LevelClear)),
+            ).in_set(GameSet::Presentation));
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub enum CatKind {
     Basic,
     Kitten,
@@ -53,14 +75,30 @@ pub enum CatKind {
 }
 
 impl CatKind {
-    pub fn random() -> Self {
+    pub fn random(rng: &mut Rng) -> Self {
         static ALL_KINDS: &[CatKind] = &[
             CatKind::Basic,
             CatKind::Kitten,
             CatKind::Chonk,
         ];
 
-        *fastrand::choice(ALL_KINDS).unwrap()
+        *rng.choice(ALL_KINDS).unwrap()
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            CatKind::Basic => "BasicCat",
+            CatKind::Kitten => "KittenCat",
+            CatKind::Chonk => "ChonkCat",
+        }
+    }
+
+    pub fn aseprite(&self, assets: &GameAssets) -> Handle<Aseprite> {
+        match self {
+            CatKind::Basic => &assets.basic_cat,
+            CatKind::Kitten => &assets.kitten,
+            CatKind::Chonk => &assets.fat_cat,
+        }.clone()
     }
 
     fn walk_speed(&self) -> f32 {
@@ -108,24 +146,46 @@ pub struct Cat {
     pub kind: CatKind,
     pub state: CatState,
     color: Color,
+    voice_pitch: f32,
     meow_timer: Timer,
+    scared_timer: Timer,
+    scared_from: Vec2,
 }
 
 impl Cat {
-    fn new(kind: CatKind) -> Self {
-        let meow_time = MEOW_RANGE.start + (fastrand::f32() * (MEOW_RANGE.end - MEOW_RANGE.start));
+    fn new(kind: CatKind, color: Color, rng: &mut Rng) -> Self {
+        let meow_time = MEOW_RANGE.start + (rng.f32() * (MEOW_RANGE.end - MEOW_RANGE.start));
+        let mut scared_timer = Timer::from_seconds(SCARED_TIME, TimerMode::Once);
+        scared_timer.finish();
         Self {
             kind,
-            state: CatState::Wander { accel_angle: fastrand::f32() * 2.0 * PI },
-            color: Color::WHITE,
+            state: CatState::Wander { accel_angle: rng.f32() * 2.0 * PI },
+            color,
+            voice_pitch: VOICE_PITCH_RANGE.start + (rng.f32() * (VOICE_PITCH_RANGE.end - VOICE_PITCH_RANGE.start)),
             meow_timer: Timer::from_seconds(meow_time, TimerMode::Once),
+            scared_timer,
+            scared_from: Vec2::ZERO,
         }
     }
 
-    fn reset_meow(&mut self) {
-        let meow_time = MEOW_RANGE.start + (fastrand::f32() * (MEOW_RANGE.end - MEOW_RANGE.start));
+    fn reset_meow(&mut self, rng: &mut Rng) {
+        let meow_time = MEOW_RANGE.start + (rng.f32() * (MEOW_RANGE.end - MEOW_RANGE.start));
         self.meow_timer = Timer::from_seconds(meow_time, TimerMode::Once);
     }
+
+    fn scare(&mut self, origin: Vec2) {
+        self.scared_from = origin;
+        self.scared_timer.reset();
+    }
+
+    fn is_scared(&self) -> bool {
+        !self.scared_timer.is_finished()
+    }
+
+    /// Speed multiplier from being barked at. Starts high and decays back to 1.0.
+    fn scared_speed_boost(&self) -> f32 {
+        1.0 + (SCARED_SPEED_BOOST - 1.0) * self.scared_timer.fraction_remaining()
+    }
 }
 
 #[derive(Component)]
@@ -169,6 +229,10 @@ impl Annoyance {
         self.current = self.current.clamp(0.0, 1.0);
     }
 
+    fn bump(&mut self, amount: f32) {
+        self.current = (self.current + amount).clamp(0.0, 1.0);
+    }
+
     fn reset(&mut self) {
         self.current = 0.0;
     }
@@ -176,25 +240,36 @@ impl Annoyance {
     fn is_annoyed(&self) -> bool {
         self.current >= 1.0
     }
+
+    /// From 0.0 (calm) to 1.0 (about to cannonball).
+    pub fn level(&self) -> f32 {
+        self.current
+    }
 }
 
-fn cat(name: &'static str, pos: Vec2, aseprite: Handle<Aseprite>, kind: CatKind) -> impl Bundle {
+/// Create a cat of the given kind. Picks a random color if one isn't given.
+pub fn cat(kind: CatKind, pos: Vec2, color: Option<Color>, rng: &mut Rng, assets: &GameAssets) -> impl Bundle {
+    let color = color.unwrap_or_else(|| random_cat_color(rng));
     (
-        Cat::new(kind),
+        Cat::new(kind, color, rng),
         Annoyance::from_cat_kind(kind),
-        Name::new(name),
+        Name::new(kind.name()),
         Transform::from_translation(pos.extend(2.0)),
         Sprite {
-            flip_x: fastrand::bool(),
+            color,
+            flip_x: rng.bool(),
             ..default()
         },
         AseAnimation {
-            aseprite,
+            aseprite: kind.aseprite(assets),
             animation: Animation::default()
                 .with_tag("idle"),
         },
         Velocity::default(),
-        collider(Collider::rectangle(30.0, 30.0), GameLayer::Cat, GameLayer::CatBox),
+        Solid {
+            half_size: Vec2::splat(CAT_BODY_HALF_SIZE),
+        },
+        collider(Collider::rectangle(30.0, 30.0), GameLayer::Cat, [GameLayer::CatBox, GameLayer::Obstacle]),
         MovementBounds {
             min: -(WORLD_SIZE.as_vec2() / 2.0) + Vec2::splat(CAT_BOUNDS),
             max: (WORLD_SIZE.as_vec2() / 2.0) - Vec2::splat(CAT_BOUNDS),
@@ -202,38 +277,40 @@ fn cat(name: &'static str, pos: Vec2, aseprite: Handle<Aseprite>, kind: CatKind)
     )
 }
 
-pub fn basic_cat(pos: Vec2, spritesheet: Handle<Aseprite>) -> impl Bundle {
-    cat("BasicCat", pos, spritesheet, CatKind::Basic)
-}
-
-pub fn kitten_cat(pos: Vec2, spritesheet: Handle<Aseprite>) -> impl Bundle {
-    cat("KittenCat", pos, spritesheet, CatKind::Kitten)
-}
-
-pub fn chonk_cat(pos: Vec2, spritesheet: Handle<Aseprite>) -> impl Bundle {
-    cat("ChonkCat", pos, spritesheet, CatKind::Chonk)
-}
-
 pub fn update_cats(
     time: Res<Time>,
-    audio: Res<Audio>,
+    mut rng: ResMut<GameRng>,
+    mut audio: SpatialAudio,
     sounds: Res<SfxAssets>,
+    mut barks: MessageReader<Bark>,
     mut cat_q: Query<(&mut Cat, &mut Annoyance, &mut CollisionLayers, &Transform, &mut Velocity)>,
-    dog_q: Query<(&Dog, &GlobalTransform), Without<Cat>>,
-    cat_box_q: Query<(&Collider, &GlobalTransform), With<CatBox>>,
+    // Transform holds the simulated positions during FixedUpdate, while GlobalTransform lags a frame behind.
+    dog_q: Query<(&Dog, &Transform), Without<Cat>>,
+    cat_box_q: Query<(&CatBox, &Collider, &Transform)>,
+    obstacle_q: Query<(&Obstacle, &Transform)>,
 ) {
     let dt = time.delta();
 
-    let (dog_recovering, dog_pos) = dog_q.single()
-        .map(|(dog, trans)| (dog.is_recovering(), Some(trans.translation().truncate())))
-        .unwrap_or((false, None));
+    let dogs: Vec<(bool, Vec2)> = dog_q.iter()
+        .map(|(dog, trans)| (dog.is_recovering(), trans.translation.truncate()))
+        .collect();
     let cat_box_data = cat_box_q.single().ok();
+    let barks: Vec<Bark> = barks.read().copied().collect();
+    let obstacles: Vec<Rect> = obstacle_q.iter()
+        .map(|(obstacle, transform)| obstacle.rect(transform.translation.truncate()))
+        .collect();
     for (mut cat, mut annoyance, mut layers, transform, mut velocity) in cat_q.iter_mut() {
         let pos = transform.translation.truncate();
 
+        // Cats only care about the closest dog.
+        let (dog_recovering, dog_pos) = dogs.iter()
+            .min_by(|(_, a), (_, b)| a.distance_squared(pos).total_cmp(&b.distance_squared(pos)))
+            .map(|(recovering, dog_pos)| (*recovering, Some(*dog_pos)))
+            .unwrap_or((false, None));
+
         // TODO: Switch to using events to check if a cat _entered_ the pen.
-        let in_pen = cat_box_data.map(|(collider, cat_box_transform)| {
-                let box_pos = cat_box_transform.translation().truncate();
+        let in_pen = cat_box_data.map(|(_, collider, cat_box_transform)| {
+                let box_pos = cat_box_transform.translation.truncate();
                 // TODO: Get cat_box rotation from transform.
                 collider.contains_point(box_pos, 0.0, pos)
             })
@@ -244,13 +321,19 @@ pub fn update_cats(
         let dog_out_of_range = dog_pos.map(|dog_pos|
             pos.distance_squared(dog_pos) > (FLEE_RANGE + FLEE_BUFFER).powi(2))
             .unwrap_or(true);
+        let barked_from = barks.iter()
+            .find(|bark| pos.distance_squared(bark.origin) < bark.radius.powi(2))
+            .map(|bark| bark.origin);
+        cat.scared_timer.tick(dt);
+        let near_obstacle = nearest_obstacle(pos, &obstacles)
+            .filter(|(_, dist)| *dist < CAT_BODY_HALF_SIZE + OBSTACLE_AVOID_RANGE);
 
         // Update cat state first.
         match &cat.state {
             CatState::Wander { .. } => {
                 if in_pen {
                     cat.state = CatState::InPen;
-                } else if !dog_recovering && dog_in_range {
+                } else if barked_from.is_some() || (!dog_recovering && dog_in_range) {
                     cat.state = CatState::Flee;
                 }
             }
@@ -262,10 +345,8 @@ pub fn update_cats(
                         timer: Timer::from_seconds(JITTER_TIME, TimerMode::Once),
                     };
                     **velocity = Vec2::ZERO;
-                    let sound = fastrand::choice(sounds.angry_cat.iter()).unwrap();
-                    audio.play(sound.clone())
-                        .with_volume(0.6);
-                } else if !dog_recovering && dog_out_of_range {
+                    audio.play_meow(&mut rng.audio, &sounds.angry_cat, pos, 0.6, cat.voice_pitch, true);
+                } else if !dog_recovering && dog_out_of_range && !cat.is_scared() {
                     // Start wandering facing the direction we were fleeing.
                     let accel_angle = velocity.to_angle() + PI;
                     cat.state = CatState::Wander { accel_angle };
@@ -300,6 +381,12 @@ pub fn update_cats(
             CatState::InPen => {},
         }
 
+        // Barks send fleeing cats running even faster, but also annoy them.
+        if let Some(origin) = barked_from && matches!(cat.state, CatState::Flee) {
+            cat.scare(origin);
+            annoyance.bump(BARK_ANNOYANCE);
+        }
+
         // Perform cat state logic.
         match &cat.state {
             CatState::Flee => {
@@ -311,34 +398,51 @@ pub fn update_cats(
             _ => {}
         }
         let cat_kind = cat.kind;
+        let flee_from = if cat.is_scared() {
+            Some(cat.scared_from)
+        } else {
+            dog_pos
+        };
+        let flee_speed = cat_kind.flee_speed() * cat.scared_speed_boost();
         match &mut cat.state {
             CatState::Wander { accel_angle }=> {
                 // Wander logic.
 
                 // Update the desired wander acceleration by a random amount.
-                let accel_angle_delta = ((fastrand::f32() * 2.0) - 1.0) * 18.0;
+                // The angle does a random walk, so it spreads with the square root of the number of steps.
+                let steps = dt.as_secs_f32() * WANDER_TUNED_RATE;
+                let accel_angle_delta = ((rng.cats.f32() * 2.0) - 1.0) * WANDER_ANGLE_JITTER * steps.sqrt();
                 *accel_angle += accel_angle_delta.to_radians();
 
                 // Update velocity to move a bit more towards the desired angle.
-                **velocity += Vec2::from_angle(*accel_angle) * cat_kind.walk_turn_radius();
+                **velocity += Vec2::from_angle(*accel_angle) * cat_kind.walk_turn_radius() * steps;
                 // And set speed to walk speed.
                 **velocity = velocity.normalize() * cat_kind.walk_speed();
 
                 // apply repulsive force if we're close to the cat box
-                if let Some((_, catbox_trans)) = cat_box_data {
-                    let catbox_pos = catbox_trans.translation().truncate();
+                if let Some((cat_box, _, catbox_trans)) = cat_box_data {
+                    let catbox_pos = catbox_trans.translation.truncate();
                     let box_to_cat = pos - catbox_pos;
-                    if box_to_cat.length() < (/*cat_box.size.x*/ 60.0 + CATBOX_BUFFER) {
+                    if box_to_cat.length() < (cat_box.size.x + CATBOX_BUFFER) {
                         **velocity = (**velocity + box_to_cat.normalize() * 150.0 / box_to_cat.length()).normalize() * cat_kind.walk_speed();
                     }
                 }
 
+                // And steer away from nearby obstacles so we don't walk into them.
+                if let Some((away, dist)) = near_obstacle {
+                    **velocity = (**velocity + away * 150.0 / dist.max(1.0)).normalize_or_zero() * cat_kind.walk_speed();
+                    *accel_angle = (Vec2::from_angle(*accel_angle) + away).to_angle();
+                }
+
                 // gizmos.arrow_2d(pos, pos + Vec2::from_angle(*accel_angle) * 20.0, Color::WHITE);
             }
             CatState::Flee => {
-                if let Some(dog_pos) = dog_pos {
-                    let flee_dir = (pos - dog_pos).normalize_or_zero();
-                    **velocity = flee_dir * cat_kind.flee_speed();
+                if let Some(flee_from) = flee_from {
+                    let mut flee_dir = (pos - flee_from).normalize_or_zero();
+                    if let Some((away, _)) = near_obstacle {
+                        flee_dir = slide_along_obstacle(flee_dir, away);
+                    }
+                    **velocity = flee_dir * flee_speed;
                 }
             }
             CatState::Jittering { timer } => {
@@ -354,7 +458,33 @@ pub fn update_cats(
     }
 }
 
+/// Find the closest obstacle. Returns the direction pointing away from it and the distance to it.
+fn nearest_obstacle(pos: Vec2, obstacles: &[Rect]) -> Option<(Vec2, f32)> {
+    obstacles.iter()
+        .map(|rect| {
+            let away = pos - pos.clamp(rect.min, rect.max);
+            (away.normalize_or_zero(), away.length())
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+}
+
+/// Redirect movement heading into an obstacle along its edge so we don't get pinned against it.
+fn slide_along_obstacle(dir: Vec2, away: Vec2) -> Vec2 {
+    let into = dir.dot(away);
+    if into >= 0.0 {
+        return dir;
+    }
+    let slide = dir - away * into;
+    if slide.length_squared() > 0.01 {
+        slide.normalize()
+    } else {
+        // Running straight into it, so pick a side.
+        away.perp()
+    }
+}
+
 fn cat_animation(
+    mut rng: ResMut<GameRng>,
     mut cat_q: Query<(&mut AseAnimation, &mut Sprite, &mut Anchor, &Cat, &Velocity)>,
 ) {
     use bevy::sprite::Anchor;
@@ -408,8 +538,8 @@ fn cat_animation(
         match &cat.state {
             CatState::Jittering { .. } => {
                 let offset = Vec2::new(
-                    (fastrand::f32() * 2.0) - 1.0,
-                    (fastrand::f32() * 2.0) - 1.0,
+                    (rng.effects.f32() * 2.0) - 1.0,
+                    (rng.effects.f32() * 2.0) - 1.0,
                 ) * JITTER_AMOUNT;
                 anchor.0 = offset / 32.0;
             }
@@ -420,6 +550,23 @@ fn cat_animation(
     }
 }
 
+fn celebrate_in_pen(
+    time: Res<Time>,
+    mut cat_q: Query<(Entity, &Cat, &mut Sprite, &mut Anchor)>,
+) {
+    // Penned cats hop around while the level clear prompt is up.
+    for (entity, cat, mut sprite, mut anchor) in cat_q.iter_mut() {
+        if cat.state != CatState::InPen {
+            continue;
+        }
+        // Offset each cat a bit so they don't all hop in sync.
+        let t = time.elapsed_secs() * CELEBRATE_HOP_SPEED + entity.index() as f32;
+        let hop = t.sin().abs() * CELEBRATE_HOP_HEIGHT;
+        anchor.0 = Vec2::new(0.0, -hop / 32.0);
+        sprite.flip_x = (t / PI) as i32 % 2 == 0;
+    }
+}
+
 fn cat_color(
     mut cat_q: Query<(&Annoyance, &Cat, &mut Sprite), (With<Cat>, Changed<Annoyance>)>,
 ) {
@@ -435,24 +582,26 @@ fn cat_color(
 
 fn cat_meows(
     time: Res<Time>,
-    audio: Res<Audio>,
+    mut rng: ResMut<GameRng>,
+    mut audio: SpatialAudio,
     sounds: Res<SfxAssets>,
-    mut cat_q: Query<&mut Cat>,
+    mut cat_q: Query<(&mut Cat, &Transform)>,
 ) {
     let dt = time.delta();
-    for mut cat in cat_q.iter_mut() {
+    for (mut cat, transform) in cat_q.iter_mut() {
         if matches!(cat.state, CatState::Jittering { .. } | CatState::Cannonballing { .. }) {
             // Don't tick in annoyed states.
             continue;
         }
 
         if cat.meow_timer.tick(dt).is_finished() {
-            audio.play(match cat.kind {
-                CatKind::Basic => sounds.basic_cat_meow.clone(),
-                CatKind::Kitten => sounds.kitten_meow.clone(),
-                CatKind::Chonk => sounds.fat_cat_meow.clone(),
-            });
-            cat.reset_meow();
+            let variants = match cat.kind {
+                CatKind::Basic => &sounds.basic_cat_meow,
+                CatKind::Kitten => &sounds.kitten_meow,
+                CatKind::Chonk => &sounds.fat_cat_meow,
+            };
+            audio.play_meow(&mut rng.audio, variants, transform.translation.truncate(), 1.0, cat.voice_pitch, false);
+            cat.reset_meow(&mut rng.cats);
         }
     }
 }
@@ -464,17 +613,7 @@ pub const CAT_COLORS: &[[f32; 3]] = &[
     [255.0 / 255.0, 193.0 / 255.0, 229.0 / 255.0], // Not quite but sort of pink.
 ];
 
-pub fn random_cat_color() -> Color {
-    let color = fastrand::choice(CAT_COLORS).unwrap();
+pub fn random_cat_color(rng: &mut Rng) -> Color {
+    let color = rng.choice(CAT_COLORS).unwrap();
     Color::srgb_from_array(*color)
 }
-
-fn init_cat_color(
-    mut cat_q: Query<(&mut Sprite, &mut Cat), Added<Sprite>>,
-) {
-    for (mut sprite, mut cat) in cat_q.iter_mut() {
-        let color = random_cat_color();
-        sprite.color = color;
-        cat.color = color;
-    }
-}
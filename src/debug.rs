@@ -1,8 +1,19 @@
 use avian2d::debug_render::*;
 use bevy::prelude::*;
+use bevy::window::{Monitor, PrimaryMonitor, PrimaryWindow};
 use bevy_egui::{egui, input::egui_wants_any_keyboard_input, EguiContextSettings, EguiContexts, EguiPrimaryContextPass};
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 
+use crate::{
+    editor::{EditorPlugin, EditorState},
+    game::GameState,
+    input::{Action, ActionState},
+    level::LevelReloadSettings,
+    replay,
+    rng::GameRng,
+    window::{self, WindowState},
+};
+
 pub struct DebugPlugin;
 
 impl Plugin for DebugPlugin {
@@ -12,6 +23,7 @@ impl Plugin for DebugPlugin {
                 bevy_egui::EguiPlugin::default(),
                 WorldInspectorPlugin::default().run_if(show_world_inspector),
                 PhysicsDebugPlugin::default(),
+                EditorPlugin,
             ))
             .insert_gizmo_config(
                 PhysicsGizmos::default(),
@@ -61,8 +73,17 @@ fn show_world_inspector(
 }
 
 fn debug_menu_bar(
+    mut commands: Commands,
     mut debug_state: ResMut<DebugState>,
+    mut editor_state: ResMut<EditorState>,
+    mut reload_settings: ResMut<LevelReloadSettings>,
     mut gizmo_config_store: ResMut<GizmoConfigStore>,
+    window_state: Res<WindowState>,
+    mut window_q: Query<&mut Window, With<PrimaryWindow>>,
+    monitor_q: Query<&Monitor, With<PrimaryMonitor>>,
+    rng: Res<GameRng>,
+    mut fixed_time: ResMut<Time<Fixed>>,
+    game_state: Res<State<GameState>>,
     mut egui_ctx: EguiContexts,
 ) {
     let ctx = egui_ctx.ctx_mut().unwrap();
@@ -77,26 +98,57 @@ fn debug_menu_bar(
                 ui.menu_button("Debug", |ui| {
                     ui.checkbox(&mut debug_state.show_world_inspector, "World Inspector");
                     ui.checkbox(&mut gizmo_config.enabled, "Debug Physics Render");
-                    // ui.checkbox(&mut debug_state.place_entity_mode, "Place Entity Mode");
+                    ui.checkbox(&mut editor_state.enabled, "Level Editor");
+                    ui.checkbox(&mut reload_settings.respawn_current_level, "Respawn Level on Reload");
+                    // Changing the tick rate mid-level would make the replay being recorded play back differently.
+                    let mut tick_rate = 1.0 / fixed_time.timestep().as_secs_f64();
+                    let in_level = *game_state.get() == GameState::Playing;
+                    let slider = egui::Slider::new(&mut tick_rate, 10.0..=240.0).text("Tick Rate");
+                    if ui.add_enabled(!in_level, slider).on_disabled_hover_text("Can't change mid-level").changed() {
+                        fixed_time.set_timestep_hz(tick_rate);
+                    }
+                });
+                ui.menu_button("Window", |ui| {
+                    let Ok(mut window) = window_q.single_mut() else {
+                        return;
+                    };
+                    ui.horizontal(|ui| {
+                        ui.label(format!("Scale: {}x", window_state.scale));
+                        if ui.button("-").clicked() {
+                            window::step_window_scale(&mut window, &window_state, -1, monitor_q.single().ok());
+                        }
+                        if ui.button("+").clicked() {
+                            window::step_window_scale(&mut window, &window_state, 1, monitor_q.single().ok());
+                        }
+                    });
+                    let mut fullscreen = window_state.fullscreen;
+                    if ui.checkbox(&mut fullscreen, "Fullscreen").changed() {
+                        window::toggle_fullscreen(&mut window, &window_state);
+                    }
                 });
+                if ui.button("Save Replay").clicked() {
+                    commands.run_system_cached(replay::save_last_replay);
+                }
+                // Show the seed so bug reports can be reproduced with --seed.
+                ui.label(format!("Seed: {}", rng.seed()));
             });
         });
 }
 
 fn toggle_debug_ui(
-    keys: Res<ButtonInput<KeyCode>>,
+    actions: Res<ActionState>,
     mut debug_state: ResMut<DebugState>,
 ) {
-    if keys.just_pressed(KeyCode::Backspace) {
+    if actions.just_pressed(Action::Debug) {
         debug_state.enabled = !debug_state.enabled;
     }
 }
 
 fn toggle_physics_debug_render(
-    keys: Res<ButtonInput<KeyCode>>,
+    actions: Res<ActionState>,
     mut gizmo_config_store: ResMut<GizmoConfigStore>,
 ) {
-    if keys.just_pressed(KeyCode::Digit0) {
+    if actions.just_pressed(Action::DebugPhysics) {
         let (gizmo_config, _) = gizmo_config_store.config_mut::<PhysicsGizmos>();
         gizmo_config.enabled = !gizmo_config.enabled;
     }
@@ -1,28 +1,47 @@
 use avian2d::prelude::{Collider, CollisionEventsEnabled, CollisionStart};
 use bevy::prelude::*;
 use bevy_aseprite_ultra::prelude::*;
-use bevy_kira_audio::{Audio, AudioControl};
 
 use crate::{
-    WORLD_SIZE, AppState,
+    WORLD_SIZE,
+    audio::SpatialAudio,
     assets::SfxAssets,
-    cats::Cat,
-    input::PlayerInput,
-    physics::{self, collider, GameLayer, MovementBounds, Velocity},
+    cats::{self, Cat},
+    game::GameSet,
+    input::{PlayerInput, MAX_PLAYERS},
+    level::LevelEntity,
+    physics::{collider, GameLayer, MovementBounds, Solid, Velocity},
+    score::LevelStats,
     utils::Blink,
 };
 
+pub const BARK_RADIUS: f32 = 110.0;
+const BARK_COOLDOWN: f32 = 1.5;
+const BARK_RING_TIME: f32 = 0.3;
+
+/// Tint for each player's dog. Player 1 keeps the original colors.
+const PLAYER_COLORS: [Color; MAX_PLAYERS] = [
+    Color::WHITE,
+    Color::srgb(0.6, 0.8, 1.0),
+    Color::srgb(1.0, 0.7, 0.7),
+    Color::srgb(0.7, 1.0, 0.6),
+];
+
 pub struct DogPlugin;
 
 impl Plugin for DogPlugin {
     fn build(&self, app: &mut App) {
         app
+            .add_message::<Bark>()
+            .add_systems(FixedUpdate, (
+                tick_recovery.before(dog_movement),
+                dog_movement,
+                dog_bark.before(cats::update_cats),
+            ).in_set(GameSet::Logic))
             .add_systems(Update, (
-                tick_recovery.before(dog_movement).chain(),
-                dog_movement.before(physics::update_movement),
-                dog_animation.after(dog_movement),
-                dog_bark,
-            ).run_if(in_state(AppState::Playing)));
+                dog_animation,
+                update_bark_rings,
+            ).in_set(GameSet::Presentation));
     }
 }
 
@@ -30,6 +49,7 @@ impl Plugin for DogPlugin {
 pub struct Dog {
     speed: f32,
     recovery_timer: Timer,
+    bark_cooldown: Timer,
 }
 
 impl Dog {
@@ -41,19 +61,50 @@ impl Dog {
     pub fn is_recovering(&self) -> bool {
         !self.recovery_timer.is_paused() && !self.recovery_timer.is_finished()
     }
+
+    pub fn can_bark(&self) -> bool {
+        self.bark_cooldown.is_finished()
+    }
+
+    /// How recharged the bark is, from 0.0 (just barked) to 1.0 (ready).
+    pub fn bark_charge(&self) -> f32 {
+        self.bark_cooldown.fraction()
+    }
+}
+
+/// Shockwave sent out when a dog barks. Cats within the radius get scared away from the origin.
+#[derive(Clone, Copy, Message)]
+pub struct Bark {
+    pub origin: Vec2,
+    pub radius: f32,
 }
 
-pub fn dog(pos: Vec2, aseprite: Handle<Aseprite>) -> impl Bundle {
+/// Visual ring that expands out from where a dog barked.
+#[derive(Component)]
+struct BarkRing {
+    radius: f32,
+    timer: Timer,
+}
+
+pub fn player_color(player: usize) -> Color {
+    PLAYER_COLORS[player % MAX_PLAYERS]
+}
+
+pub fn dog(pos: Vec2, player: usize, aseprite: Handle<Aseprite>) -> impl Bundle {
     let mut recovery_timer = Timer::from_seconds(0.5, TimerMode::Once);
     recovery_timer.pause();
+    let mut bark_cooldown = Timer::from_seconds(BARK_COOLDOWN, TimerMode::Once);
+    bark_cooldown.finish();
     (
         Name::new("Dog"),
         Dog {
             speed: 150.0,
             recovery_timer,
+            bark_cooldown,
         },
         Transform::from_translation(pos.extend(3.0)),
         Sprite {
+            color: player_color(player),
             flip_x: true,
             ..default()
         },
@@ -63,9 +114,15 @@ pub fn dog(pos: Vec2, aseprite: Handle<Aseprite>) -> impl Bundle {
                 .with_tag("idle_front"),
         },
         Velocity::default(),
-        collider(Collider::rectangle(30.0, 30.0), GameLayer::Dog, GameLayer::Cat),
+        Solid {
+            half_size: Vec2::splat(10.0),
+        },
+        collider(Collider::rectangle(30.0, 30.0), GameLayer::Dog, [GameLayer::Cat, GameLayer::Obstacle]),
         CollisionEventsEnabled,
-        PlayerInput::default(),
+        PlayerInput {
+            player,
+            ..default()
+        },
         MovementBounds {
             min: -(WORLD_SIZE.as_vec2() / 2.0) + Vec2::new(0.0, 0.0),
             max: (WORLD_SIZE.as_vec2() / 2.0) - Vec2::new(0.0, 0.0),
@@ -84,12 +141,13 @@ fn dog_movement(
 
 pub fn dog_intersects_cat(
     collision: On<CollisionStart>,
-    audio: Res<Audio>,
+    audio: SpatialAudio,
     sounds: Res<SfxAssets>,
-    mut dog_q: Query<(&mut Dog, &mut Blink)>,
+    mut stats: ResMut<LevelStats>,
+    mut dog_q: Query<(&mut Dog, &mut Blink, &Transform)>,
     cat_q: Query<&Cat, Without<Dog>>,
 ) {
-    let Ok((mut dog, mut blink)) = dog_q.get_mut(collision.collider1) else {
+    let Ok((mut dog, mut blink, transform)) = dog_q.get_mut(collision.collider1) else {
         return;
     };
 
@@ -101,7 +159,8 @@ pub fn dog_intersects_cat(
     if let Ok(cat) = cat_q.get(other_entity) && cat.state.is_cannonballing() {
         dog.start_recovery();
         blink.enable();
-        audio.play(sounds.dog_yip.clone());
+        stats.times_hit += 1;
+        audio.play(sounds.dog_yip.clone(), transform.translation.truncate());
     }
 }
 
@@ -139,14 +198,59 @@ fn dog_animation(
 }
 
 fn dog_bark(
-    audio: Res<Audio>,
+    mut commands: Commands,
+    time: Res<Time>,
+    audio: SpatialAudio,
     sfx: Res<SfxAssets>,
-    dog_q: Query<&PlayerInput, With<Dog>>,
+    mut barks: MessageWriter<Bark>,
+    mut stats: ResMut<LevelStats>,
+    mut dog_q: Query<(&mut Dog, &mut PlayerInput, &Transform)>,
+) {
+    let dt = time.delta();
+    for (mut dog, mut input, transform) in dog_q.iter_mut() {
+        dog.bark_cooldown.tick(dt);
+        // Use up the bark so it only happens on one tick.
+        let bark = std::mem::take(&mut input.bark);
+        if !bark || !dog.can_bark() {
+            continue;
+        }
+        dog.bark_cooldown.reset();
+        stats.barks += 1;
+
+        let origin = transform.translation.truncate();
+        barks.write(Bark {
+            origin,
+            radius: BARK_RADIUS,
+        });
+        commands.spawn((
+            LevelEntity,
+            Name::new("BarkRing"),
+            BarkRing {
+                radius: BARK_RADIUS,
+                timer: Timer::from_seconds(BARK_RING_TIME, TimerMode::Once),
+            },
+            Transform::from_translation(origin.extend(4.0)),
+        ));
+        audio.play(sfx.dog_woof.clone(), origin);
+    }
+}
+
+fn update_bark_rings(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut gizmos: Gizmos,
+    mut ring_q: Query<(Entity, &mut BarkRing, &Transform)>,
 ) {
-    let bark = dog_q.single()
-        .map(|input| input.bark)
-        .unwrap_or(false);
-    if bark {
-        audio.play(sfx.dog_woof.clone());
+    let dt = time.delta();
+    for (entity, mut ring, transform) in ring_q.iter_mut() {
+        if ring.timer.tick(dt).is_finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        // Expand the ring out to the bark radius while fading it out.
+        let t = ring.timer.fraction();
+        let color = Color::WHITE.with_alpha(1.0 - t);
+        gizmos.circle_2d(transform.translation.truncate(), ring.radius * t, color);
     }
 }
@@ -0,0 +1,323 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_egui::{egui, input::egui_wants_any_pointer_input, EguiContexts, EguiPrimaryContextPass};
+
+use crate::{
+    AppState,
+    cats::CatKind,
+    furniture::FurnitureKind,
+    level::{CatSpawn, CurrentLevel, LevelData, Levels, ObstacleData, RespawnLevel},
+};
+
+const DEFAULT_SAVE_PATH: &str = "assets/all_levels.level.ron";
+const PICK_RADIUS: f32 = 15.0;
+
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<EditorState>()
+            .add_systems(EguiPrimaryContextPass,
+                editor_window.run_if(editor_enabled)
+            )
+            .add_systems(Update, (
+                pause_time_while_editing,
+                (
+                    editor_mouse_input.run_if(not(egui_wants_any_pointer_input)),
+                    draw_editor_gizmos,
+                ).run_if(editor_enabled),
+            ));
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum EditorTool {
+    Move,
+    PlaceCat(CatKind),
+    PlaceFurniture(FurnitureKind),
+    Delete,
+    DogStart,
+}
+
+/// Something in the level that can be dragged around with the Move tool.
+#[derive(Clone, Copy)]
+enum DragTarget {
+    Cat(usize),
+    Obstacle(usize),
+    Pen,
+    DogStart,
+}
+
+#[derive(Resource)]
+pub struct EditorState {
+    pub enabled: bool,
+    tool: EditorTool,
+    dragging: Option<DragTarget>,
+    save_path: String,
+    status: String,
+}
+
+impl Default for EditorState {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            tool: EditorTool::Move,
+            dragging: None,
+            save_path: DEFAULT_SAVE_PATH.into(),
+            status: String::new(),
+        }
+    }
+}
+
+fn editor_enabled(
+    editor_state: Res<EditorState>,
+    app_state: Res<State<AppState>>,
+) -> bool {
+    editor_state.enabled && *app_state.get() == AppState::Playing
+}
+
+fn pause_time_while_editing(
+    editor_state: Res<EditorState>,
+    app_state: Res<State<AppState>>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    // Freeze the level while editing so cats stay where they were placed.
+    let editing = editor_state.enabled && *app_state.get() == AppState::Playing;
+    if editing && !time.is_paused() {
+        time.pause();
+    } else if !editing && time.is_paused() {
+        time.unpause();
+    }
+}
+
+fn editor_window(
+    mut editor_state: ResMut<EditorState>,
+    mut levels: ResMut<Levels>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut respawn: MessageWriter<RespawnLevel>,
+    mut egui_ctx: EguiContexts,
+) {
+    let Ok(ctx) = egui_ctx.ctx_mut() else {
+        return;
+    };
+    let editor_state = &mut *editor_state;
+    let num_levels = levels.len();
+    let Some(level) = levels.get_mut(current_level.index) else {
+        return;
+    };
+
+    let mut changed = false;
+    let mut save = false;
+    egui::Window::new("Level Editor")
+        .default_pos((10.0, 40.0))
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("<").clicked() && current_level.index > 0 {
+                    current_level.index -= 1;
+                    changed = true;
+                }
+                ui.label(format!("Level {}/{}", current_level.index, num_levels.saturating_sub(1)));
+                if ui.button(">").clicked() && current_level.index + 1 < num_levels {
+                    current_level.index += 1;
+                    changed = true;
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Name");
+                ui.text_edit_singleline(&mut level.name);
+            });
+
+            ui.separator();
+            ui.label("Tool");
+            let tool = &mut editor_state.tool;
+            ui.radio_value(tool, EditorTool::Move, "Move");
+            ui.radio_value(tool, EditorTool::Delete, "Delete");
+            ui.radio_value(tool, EditorTool::DogStart, "Set Dog Start");
+            ui.horizontal(|ui| {
+                ui.radio_value(tool, EditorTool::PlaceCat(CatKind::Basic), "Basic");
+                ui.radio_value(tool, EditorTool::PlaceCat(CatKind::Kitten), "Kitten");
+                ui.radio_value(tool, EditorTool::PlaceCat(CatKind::Chonk), "Chonk");
+            });
+            ui.horizontal(|ui| {
+                ui.radio_value(tool, EditorTool::PlaceFurniture(FurnitureKind::Couch), "Couch");
+                ui.radio_value(tool, EditorTool::PlaceFurniture(FurnitureKind::Table), "Table");
+                ui.radio_value(tool, EditorTool::PlaceFurniture(FurnitureKind::Plant), "Plant");
+            });
+
+            ui.separator();
+            ui.label("Random Cats");
+            ui.horizontal(|ui| {
+                changed |= ui.add(egui::DragValue::new(&mut level.basic).range(0..=50).prefix("Basic: ")).changed();
+                changed |= ui.add(egui::DragValue::new(&mut level.kitten).range(0..=50).prefix("Kitten: ")).changed();
+                changed |= ui.add(egui::DragValue::new(&mut level.chonk).range(0..=50).prefix("Chonk: ")).changed();
+            });
+            ui.horizontal(|ui| {
+                ui.label("Pen Size");
+                changed |= ui.add(egui::DragValue::new(&mut level.pen.size.x).range(20.0..=200.0)).changed();
+                changed |= ui.add(egui::DragValue::new(&mut level.pen.size.y).range(20.0..=200.0)).changed();
+            });
+            if ui.button("Respawn").clicked() {
+                changed = true;
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut editor_state.save_path);
+                save = ui.button("Save").clicked();
+            });
+            if !editor_state.status.is_empty() {
+                ui.label(&editor_state.status);
+            }
+        });
+
+    if save {
+        editor_state.status = match save_levels(&levels, &editor_state.save_path) {
+            Ok(()) => format!("Saved to {}", editor_state.save_path),
+            Err(e) => format!("Failed to save: {}", e),
+        };
+    }
+    if changed {
+        respawn.write(RespawnLevel);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_levels(levels: &Levels, path: &str) -> Result<(), String> {
+    let pretty_config = ron::ser::PrettyConfig::default();
+    let levels_str = ron::ser::to_string_pretty(levels, pretty_config)
+        .map_err(|e| e.to_string())?;
+    std::fs::write(path, levels_str)
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn save_levels(_levels: &Levels, _path: &str) -> Result<(), String> {
+    Err("Saving levels is not supported on web".into())
+}
+
+fn editor_mouse_input(
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut editor_state: ResMut<EditorState>,
+    mut levels: ResMut<Levels>,
+    current_level: Res<CurrentLevel>,
+    mut respawn: MessageWriter<RespawnLevel>,
+    window_q: Query<&Window, With<PrimaryWindow>>,
+    camera_q: Query<(&Camera, &GlobalTransform)>,
+) {
+    let Some(level) = levels.get_mut(current_level.index) else {
+        return;
+    };
+    let Some(cursor_pos) = cursor_world_pos(&window_q, &camera_q) else {
+        return;
+    };
+
+    if mouse.just_pressed(MouseButton::Left) {
+        match editor_state.tool {
+            EditorTool::Move => {
+                editor_state.dragging = pick(level, cursor_pos);
+            }
+            EditorTool::PlaceCat(kind) => {
+                level.cats.push(CatSpawn {
+                    kind,
+                    pos: cursor_pos,
+                    color: None,
+                });
+                respawn.write(RespawnLevel);
+            }
+            EditorTool::PlaceFurniture(kind) => {
+                level.obstacles.push(ObstacleData {
+                    kind,
+                    pos: cursor_pos,
+                    size: None,
+                });
+                respawn.write(RespawnLevel);
+            }
+            EditorTool::Delete => {
+                match pick(level, cursor_pos) {
+                    Some(DragTarget::Cat(i)) => {
+                        level.cats.remove(i);
+                    }
+                    Some(DragTarget::Obstacle(i)) => {
+                        level.obstacles.remove(i);
+                    }
+                    Some(DragTarget::DogStart) => {
+                        level.dog_start = None;
+                    }
+                    Some(DragTarget::Pen) | None => return,
+                }
+                respawn.write(RespawnLevel);
+            }
+            EditorTool::DogStart => {
+                level.dog_start = Some(cursor_pos);
+                respawn.write(RespawnLevel);
+            }
+        }
+    }
+
+    if let Some(target) = editor_state.dragging {
+        // The level may have been reloaded since the drag started, so what was picked could be gone.
+        let pos = match target {
+            DragTarget::Cat(i) => level.cats.get_mut(i).map(|cat| &mut cat.pos),
+            DragTarget::Obstacle(i) => level.obstacles.get_mut(i).map(|obstacle| &mut obstacle.pos),
+            DragTarget::Pen => Some(&mut level.pen.pos),
+            DragTarget::DogStart => Some(level.dog_start.insert(cursor_pos)),
+        };
+        let Some(pos) = pos else {
+            editor_state.dragging = None;
+            return;
+        };
+        *pos = cursor_pos;
+        if mouse.just_released(MouseButton::Left) {
+            editor_state.dragging = None;
+            respawn.write(RespawnLevel);
+        }
+    }
+}
+
+fn cursor_world_pos(
+    window_q: &Query<&Window, With<PrimaryWindow>>,
+    camera_q: &Query<(&Camera, &GlobalTransform)>,
+) -> Option<Vec2> {
+    let cursor_pos = window_q.single().ok()?.cursor_position()?;
+    let (camera, camera_transform) = camera_q.single().ok()?;
+    camera.viewport_to_world_2d(camera_transform, cursor_pos).ok()
+}
+
+/// Find what's under the cursor. Cats are checked first since they're the smallest.
+fn pick(level: &LevelData, pos: Vec2) -> Option<DragTarget> {
+    if let Some(i) = level.cats.iter().position(|cat| cat.pos.distance(pos) < PICK_RADIUS) {
+        return Some(DragTarget::Cat(i));
+    }
+    if level.dog_start.is_some() && level.dog_start().distance(pos) < PICK_RADIUS {
+        return Some(DragTarget::DogStart);
+    }
+    if let Some(i) = level.obstacles.iter().position(|obstacle| obstacle.rect().contains(pos)) {
+        return Some(DragTarget::Obstacle(i));
+    }
+    if Rect::from_center_size(level.pen.pos, level.pen.size).contains(pos) {
+        return Some(DragTarget::Pen);
+    }
+    None
+}
+
+fn draw_editor_gizmos(
+    mut gizmos: Gizmos,
+    levels: Res<Levels>,
+    current_level: Res<CurrentLevel>,
+) {
+    use bevy::color::palettes::css;
+
+    let Some(level) = levels.get(current_level.index) else {
+        return;
+    };
+
+    gizmos.rect_2d(level.pen.pos, level.pen.size, css::LIME);
+    gizmos.cross_2d(level.dog_start(), 8.0, css::ORANGE);
+    for obstacle in &level.obstacles {
+        gizmos.rect_2d(obstacle.pos, obstacle.size(), css::YELLOW);
+    }
+    for cat in &level.cats {
+        gizmos.circle_2d(cat.pos, PICK_RADIUS, css::AQUA);
+    }
+}
@@ -0,0 +1,58 @@
+use avian2d::prelude::Collider;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::physics::{collider, GameLayer, Obstacle};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum FurnitureKind {
+    Couch,
+    #[default]
+    Table,
+    Plant,
+}
+
+impl FurnitureKind {
+    fn name(&self) -> &'static str {
+        match self {
+            FurnitureKind::Couch => "Couch",
+            FurnitureKind::Table => "Table",
+            FurnitureKind::Plant => "Plant",
+        }
+    }
+
+    pub fn default_size(&self) -> Vec2 {
+        match self {
+            FurnitureKind::Couch => Vec2::new(90.0, 30.0),
+            FurnitureKind::Table => Vec2::new(50.0, 40.0),
+            FurnitureKind::Plant => Vec2::new(16.0, 16.0),
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            FurnitureKind::Couch => Color::srgb(0.55, 0.25, 0.25),
+            FurnitureKind::Table => Color::srgb(0.45, 0.3, 0.2),
+            FurnitureKind::Plant => Color::srgb(0.25, 0.5, 0.25),
+        }
+    }
+}
+
+/// A piece of furniture that blocks the dog and cats.
+pub fn furniture(kind: FurnitureKind, pos: Vec2, size: Vec2) -> impl Bundle {
+    (
+        Name::new(kind.name()),
+        Obstacle {
+            half_size: size / 2.0,
+        },
+        Sprite {
+            color: kind.color(),
+            custom_size: Some(size),
+            ..default()
+        },
+        Transform::from_translation(pos.extend(1.0)),
+        // Blocking is done by `Obstacle`, but the collider lets physics queries, debug rendering and
+        // collision events see it too.
+        collider(Collider::rectangle(size.x, size.y), GameLayer::Obstacle, [GameLayer::Dog, GameLayer::Cat]),
+    )
+}
@@ -1,4 +1,3 @@
-use avian2d::prelude::Collider;
 use bevy::prelude::*;
 
 use crate::{
@@ -6,8 +5,10 @@ use crate::{
     assets::GameAssets,
     cats::{self, Cat, CatState},
     dog::DogPlugin,
-    level::{CurrentLevel, Levels},
-    physics::{collider, GameLayer},
+    dog::Dog,
+    input::{Action, ActionState},
+    level::{CurrentLevel, LevelEntity, Levels},
+    score::ScorePlugin,
 };
 
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, States)]
@@ -19,21 +20,58 @@ pub enum GameState {
     Victory,
 }
 
+/// Whether gameplay is paused. Only exists while in `GameState::Playing`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, SubStates)]
+#[source(GameState = GameState::Playing)]
+pub enum PauseState {
+    #[default]
+    Running,
+    Paused,
+}
+
+/// Ordering for gameplay systems. `Input` and `Presentation` run every frame in `Update`,
+/// while `Logic` and `Movement` run in `FixedUpdate` and only while a level is being played.
+/// `Input` also runs at the start of each tick to record and replay inputs.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GameSet {
+    Input,
+    Logic,
+    Movement,
+    Presentation,
+}
+
+/// How many times per second gameplay is simulated.
+pub const DEFAULT_TICK_RATE: f64 = 60.0;
+
 pub struct GamePlugin;
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app
             .init_state::<GameState>()
-            .add_plugins(DogPlugin)
+            .add_sub_state::<PauseState>()
+            .insert_resource(Time::<Fixed>::from_hz(DEFAULT_TICK_RATE))
+            .configure_sets(Update, (
+                GameSet::Input,
+                GameSet::Presentation,
+            ).chain().run_if(in_state(AppState::Playing)))
+            .configure_sets(FixedUpdate, (
+                GameSet::Input,
+                GameSet::Logic,
+                GameSet::Movement,
+            ).chain().run_if(in_state(AppState::Playing).and(in_state(PauseState::Running))))
+            .add_plugins((DogPlugin, ScorePlugin))
             .init_resource::<CatStats>()
             .add_systems(OnEnter(AppState::Playing), setup_game)
+            .add_systems(OnExit(AppState::Playing), teardown_game)
+            .add_systems(FixedUpdate, update_cat_stats.after(cats::update_cats).in_set(GameSet::Logic))
             .add_systems(Update, (
-                (
-                    update_cat_stats,
-                    check_state_change,
-                ).after(cats::update_cats).chain(),
-            ).run_if(in_state(AppState::Playing)));
+                // Runs in every GameState to advance past the level clear prompt.
+                check_state_change
+                    .after(GameSet::Input)
+                    .before(GameSet::Presentation)
+                    .run_if(in_state(AppState::Playing).and(not(in_state(PauseState::Paused)))),
+            ));
     }
 }
 
@@ -58,7 +96,12 @@ impl CatStats {
 }
 
 #[derive(Component)]
-pub struct CatBox;
+pub struct CatBox {
+    pub size: Vec2,
+}
+
+#[derive(Component)]
+struct Floor;
 
 fn setup_game(
     mut commands: Commands,
@@ -69,6 +112,7 @@ fn setup_game(
 
     // Spawn floor.
     commands.spawn((
+        Floor,
         Name::new("Floor"),
         Sprite {
             image: assets.floor.clone(),
@@ -84,20 +128,27 @@ fn setup_game(
             .with_scale(Vec3::new(2.0, 2.0, 1.0)),
     ));
 
-    // Spawn cat_box.
-    commands.spawn((
-        CatBox,
-        Name::new("CatBox"),
-        Sprite::from_image(assets.cat_box.clone()),
-        Transform::from_xyz(0.0, 0.0, -0.5),
-        collider(Collider::rectangle(60.0, 60.0), GameLayer::CatBox, GameLayer::Cat),
-    ));
-
     next_game_state.set(GameState::Playing);
 }
 
+fn teardown_game(
+    mut commands: Commands,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut current_level: ResMut<CurrentLevel>,
+    game_q: Query<Entity, Or<(With<Cat>, With<Dog>, With<CatBox>, With<LevelEntity>, With<Floor>)>>,
+) {
+    debug!("Teardown game");
+
+    for entity in game_q.iter() {
+        commands.entity(entity).despawn();
+    }
+    *current_level = CurrentLevel::default();
+    next_game_state.set(GameState::None);
+}
+
 pub fn update_cat_stats(
     mut cat_stats: ResMut<CatStats>,
+    mut current_level: ResMut<CurrentLevel>,
     cats_q: Query<&Cat>,
 ) {
     // TODO: Update on CatState changes instead of every frame?
@@ -106,19 +157,20 @@ pub fn update_cat_stats(
         .filter(|cat| cat.state == CatState::InPen)
         .count()
         as u32;
+    current_level.cats_herded = cat_stats.in_pen as u8;
 }
 
 pub fn check_state_change(
     game_state: Res<State<GameState>>,
     mut next_game_state: ResMut<NextState<GameState>>,
-    keys: Res<ButtonInput<KeyCode>>,
+    actions: Res<ActionState>,
     cat_stats: Res<CatStats>,
     current_level: Res<CurrentLevel>,
     levels: Res<Levels>,
 ) {
     match game_state.get() {
         GameState::Playing => {
-            let level_clear = cat_stats.all_penned() || keys.just_pressed(KeyCode::Tab);
+            let level_clear = cat_stats.all_penned() || actions.just_pressed(Action::DebugSkipLevel);
             if !level_clear {
                 return;
             }
@@ -130,7 +182,7 @@ pub fn check_state_change(
             }
         }
         _ => {
-            if keys.just_pressed(KeyCode::Enter) {
+            if actions.just_pressed(Action::Confirm) {
                 next_game_state.set(GameState::Playing);
             }
         }
@@ -0,0 +1,243 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::asset::io::file::FileAssetReader;
+use bevy::state::app::StatesPlugin;
+use bevy::time::TimeUpdateStrategy;
+
+use crate::{
+    AppState,
+    assets::{GameAssets, SfxAssets},
+    audio,
+    autopilot,
+    cats,
+    game::{self, DEFAULT_TICK_RATE, CatStats, GameSet, GameState},
+    input::{ActionState, InputDevice, Players},
+    level::{self, CurrentLevel, Levels},
+    log,
+    physics,
+    replay::{self, Replay},
+    rng,
+    score::{LevelResult, LevelStats},
+};
+
+/// Game time to give up on a level after.
+pub const DEFAULT_MAX_TIME: f32 = 300.0;
+const LEVELS_PATH: &str = "all_levels.level.ron";
+
+/// What to simulate when running without a window, e.g. `--headless --autopilot --level 3 --max-time 60`.
+pub struct HeadlessOptions {
+    pub seed: Option<u64>,
+    /// Simulate only this level instead of every level.
+    pub level: Option<usize>,
+    pub players: usize,
+    pub max_time: f32,
+    /// Let the autopilot drive the dogs.
+    pub autopilot: bool,
+    /// Feed inputs from a replay. Its level and players take priority.
+    pub replay: Option<Replay>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Cleared {
+        result: LevelResult,
+        times_hit: u32,
+        barks: u32,
+    },
+    TimedOut {
+        in_pen: u32,
+        total: u32,
+    },
+}
+
+/// Simulate levels at a fixed timestep without a window, audio device or GPU, and print how each one went.
+/// Fails if any level isn't cleared in time, so it can be run in CI.
+pub fn run(options: HeadlessOptions) -> AppExit {
+    let mut levels = match load_levels() {
+        Ok(levels) => levels,
+        Err(e) => {
+            eprintln!("Could not load levels: {}", e);
+            return AppExit::error();
+        }
+    };
+    if let Some(warning) = options.replay.as_ref().and_then(|replay| replay.restore_level(&mut levels)) {
+        eprintln!("{}", warning);
+    }
+    let seed = options.seed.unwrap_or_else(|| fastrand::u64(..));
+    let level_indices: Vec<usize> = match (&options.replay, options.level) {
+        (Some(replay), _) => vec![replay.level_index],
+        (None, Some(level)) => vec![level],
+        // The first level is only for testing.
+        (None, None) => (1..levels.len()).collect(),
+    };
+
+    println!("Simulating {} level(s) with seed {}", level_indices.len(), seed);
+    let mut all_cleared = true;
+    for (i, level_index) in level_indices.into_iter().enumerate() {
+        let Some(level) = levels.get(level_index) else {
+            println!("Level {}: doesn't exist, there are {} levels", level_index, levels.len());
+            all_cleared = false;
+            continue;
+        };
+        // Logging can only be set up once per process.
+        let outcome = simulate_level(&options, &levels, level_index, seed, i == 0);
+        match outcome {
+            Outcome::Cleared { result, times_hit, barks } => println!(
+                "Level {} ({}): cleared in {:.2}s, score {}, {} stars, hit {} times, {} barks",
+                level_index, level.name, result.time.as_secs_f32(), result.score, result.stars, times_hit, barks,
+            ),
+            Outcome::TimedOut { in_pen, total } => {
+                println!(
+                    "Level {} ({}): timed out after {}s with {}/{} cats herded",
+                    level_index, level.name, options.max_time, in_pen, total,
+                );
+                all_cleared = false;
+            }
+        }
+    }
+
+    if all_cleared {
+        AppExit::Success
+    } else {
+        AppExit::error()
+    }
+}
+
+/// Read the levels file straight from disk, since there's no loading screen to wait on.
+fn load_levels() -> Result<Levels, String> {
+    let path = FileAssetReader::get_base_path().join("assets").join(LEVELS_PATH);
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    ron::from_str(&contents).map_err(|e| e.to_string())
+}
+
+fn simulate_level(options: &HeadlessOptions, levels: &Levels, level_index: usize, seed: u64, init_logging: bool) -> Outcome {
+    let mut app = level_app(options, levels, level_index, seed, init_logging);
+    run_level(&mut app, options)
+}
+
+fn tick_rate(options: &HeadlessOptions) -> f64 {
+    options.replay.as_ref().map_or(DEFAULT_TICK_RATE, |replay| replay.tick_rate)
+}
+
+/// Set up an app that goes straight into the level.
+fn level_app(options: &HeadlessOptions, levels: &Levels, level_index: usize, seed: u64, init_logging: bool) -> App {
+    let tick_rate = tick_rate(options);
+    let num_players = options.replay.as_ref().map_or(options.players, |replay| replay.num_players).max(1);
+    let timestep = Duration::from_secs_f64(1.0 / tick_rate);
+
+    let mut app = App::new();
+    if init_logging {
+        app.add_plugins(log::log_plugin());
+    }
+    app
+        .add_plugins((
+            MinimalPlugins,
+            StatesPlugin,
+            TransformPlugin,
+            AssetPlugin::default(),
+            // Carries on without sound when there's no audio device.
+            bevy_kira_audio::AudioPlugin,
+        ))
+        // Normally added by the render plugins. Physics can look up meshes for colliders.
+        .init_asset::<Mesh>()
+        .init_asset::<Levels>()
+
+        // Go straight into the level.
+        .insert_state(AppState::Playing)
+        .add_plugins((
+            audio::AudioPlugin::new(default()),
+            physics::PhysicsPlugin,
+            rng::RngPlugin::new(Some(seed)),
+            game::GamePlugin,
+            level::LevelPlugin,
+            cats::CatsPlugin,
+            autopilot::AutopilotPlugin::new(options.autopilot),
+        ))
+        // Nothing is drawn, so there's no need for animations and effects.
+        .configure_sets(Update, GameSet::Presentation.run_if(|| false))
+
+        // Assets are only used for drawing and sounds, so empty handles are enough.
+        .insert_resource(GameAssets::default())
+        .insert_resource(SfxAssets::default())
+        .insert_resource(levels.clone())
+        .init_resource::<ActionState>()
+        .insert_resource(Players {
+            devices: vec![InputDevice::Keyboard; num_players],
+        })
+        .insert_resource(CurrentLevel {
+            start_index: Some(level_index),
+            ..default()
+        })
+        // Advance exactly one gameplay tick per update.
+        .insert_resource(Time::<Fixed>::from_duration(timestep))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(timestep));
+    if let Some(replay) = &options.replay {
+        app.add_plugins(replay::ReplayPlugin::new(Some(replay.clone())));
+    }
+    app
+}
+
+/// Tick the level until it's cleared or time runs out.
+fn run_level(app: &mut App, options: &HeadlessOptions) -> Outcome {
+    app.finish();
+    app.cleanup();
+
+    // Rounded, since a recorded tick rate can be a hair off from the one it was recorded at.
+    let max_ticks = (options.max_time as f64 * tick_rate(options)).round() as u64;
+    for _ in 0..max_ticks {
+        app.update();
+        let world = app.world();
+        if matches!(world.resource::<State<GameState>>().get(), GameState::LevelClear | GameState::Victory) {
+            let stats = world.resource::<LevelStats>();
+            return Outcome::Cleared {
+                result: world.resource::<LevelResult>().clone(),
+                times_hit: stats.times_hit,
+                barks: stats.barks,
+            };
+        }
+    }
+
+    let cat_stats = app.world().resource::<CatStats>();
+    Outcome::TimedOut {
+        in_pen: cat_stats.in_pen(),
+        total: cat_stats.total(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::Recorder;
+
+    #[test]
+    fn replay_reproduces_the_recorded_outcome() {
+        let levels = load_levels().expect("Could not load levels");
+        let seed = 1234;
+        let level_index = 1;
+        let recording = HeadlessOptions {
+            seed: Some(seed),
+            level: Some(level_index),
+            players: 2,
+            max_time: 60.0,
+            autopilot: true,
+            replay: None,
+        };
+
+        // Record the autopilot, without ReplayPlugin so nothing gets saved to disk.
+        let mut app = level_app(&recording, &levels, level_index, seed, false);
+        app
+            .init_resource::<Recorder>()
+            .add_systems(FixedUpdate, replay::record_inputs.in_set(GameSet::Input));
+        let recorded = run_level(&mut app, &recording);
+        let replay = app.world().resource::<Recorder>().replay.clone();
+
+        let playback = HeadlessOptions {
+            autopilot: false,
+            replay: Some(replay),
+            ..recording
+        };
+        assert_eq!(simulate_level(&playback, &levels, level_index, seed, false), recorded);
+    }
+}
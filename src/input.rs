@@ -1,75 +1,492 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
 use bevy::prelude::*;
+use bevy::input::InputSystems;
 use bevy_egui::EguiContexts;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    autopilot::Autopilot,
+    game::{GameSet, PauseState},
+};
 
-pub struct InputPlugin;
+pub struct InputPlugin {
+    saved_input_map: InputMap,
+    saved_gamepad_config: GamepadConfig,
+}
+
+impl InputPlugin {
+    pub fn new(saved_input_map: InputMap, saved_gamepad_config: GamepadConfig) -> Self {
+        Self {
+            saved_input_map,
+            saved_gamepad_config,
+        }
+    }
+}
 
 impl Plugin for InputPlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_systems(PreUpdate, read_player_input);
+            .insert_resource(self.saved_input_map.clone())
+            .insert_resource(self.saved_gamepad_config.clone())
+            .init_resource::<ActionState>()
+            .init_resource::<Players>()
+            .add_systems(PreUpdate, update_action_state.after(InputSystems))
+            .add_systems(Update, read_player_input.in_set(GameSet::Input).run_if(in_state(PauseState::Running)));
     }
 }
 
-#[derive(Default, Component, Reflect)]
-pub struct PlayerInput {
-    pub movement: Vec2,
-    pub bark: bool,
+/// Something the player can do, independent of which key or button does it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Bark,
+    Confirm,
+    Back,
+    Pause,
+    ScaleUp,
+    ScaleDown,
+    Fullscreen,
+    Debug,
+    DebugPhysics,
+    DebugSkipLevel,
 }
 
-pub fn read_player_input(
+impl Action {
+    pub const ALL: [Action; 14] = [
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Bark,
+        Action::Confirm,
+        Action::Back,
+        Action::Pause,
+        Action::ScaleUp,
+        Action::ScaleDown,
+        Action::Fullscreen,
+        Action::Debug,
+        Action::DebugPhysics,
+        Action::DebugSkipLevel,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::MoveUp => "Move Up",
+            Action::MoveDown => "Move Down",
+            Action::MoveLeft => "Move Left",
+            Action::MoveRight => "Move Right",
+            Action::Bark => "Bark",
+            Action::Confirm => "Confirm",
+            Action::Back => "Back",
+            Action::Pause => "Pause",
+            Action::ScaleUp => "Window Scale Up",
+            Action::ScaleDown => "Window Scale Down",
+            Action::Fullscreen => "Fullscreen",
+            Action::Debug => "Debug UI",
+            Action::DebugPhysics => "Debug Physics",
+            Action::DebugSkipLevel => "Skip Level",
+        }
+    }
+
+    fn default_binding(&self) -> Binding {
+        use GamepadButton as B;
+        use KeyCode as K;
+
+        let (keys, buttons) = match self {
+            Action::MoveUp => (vec![K::KeyW, K::ArrowUp], vec![B::DPadUp]),
+            Action::MoveDown => (vec![K::KeyS, K::ArrowDown], vec![B::DPadDown]),
+            Action::MoveLeft => (vec![K::KeyA, K::ArrowLeft], vec![B::DPadLeft]),
+            Action::MoveRight => (vec![K::KeyD, K::ArrowRight], vec![B::DPadRight]),
+            Action::Bark => (vec![K::Space], vec![B::South]),
+            Action::Confirm => (vec![K::Enter], vec![B::South]),
+            Action::Back => (vec![K::Escape, K::Tab], vec![B::East]),
+            Action::Pause => (vec![K::Escape], vec![B::Start]),
+            Action::ScaleUp => (vec![K::Equal, K::NumpadAdd], vec![]),
+            Action::ScaleDown => (vec![K::Minus, K::NumpadSubtract], vec![]),
+            Action::Fullscreen => (vec![K::F11], vec![]),
+            Action::Debug => (vec![K::Backspace], vec![]),
+            Action::DebugPhysics => (vec![K::Digit0], vec![]),
+            Action::DebugSkipLevel => (vec![K::Tab], vec![]),
+        };
+        Binding {
+            keys,
+            buttons,
+        }
+    }
+}
+
+/// Keys and gamepad buttons that trigger an action.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Binding {
+    pub keys: Vec<KeyCode>,
+    pub buttons: Vec<GamepadButton>,
+}
+
+/// Bindings for every action. Saved as part of the settings.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, Resource)]
+#[serde(default)]
+pub struct InputMap {
+    bindings: BTreeMap<Action, Binding>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        Self {
+            bindings: Action::ALL.iter()
+                .map(|action| (*action, action.default_binding()))
+                .collect(),
+        }
+    }
+}
+
+impl InputMap {
+    /// Get the binding for an action. Actions missing from old save data use the default.
+    pub fn binding(&self, action: Action) -> Binding {
+        self.bindings.get(&action)
+            .cloned()
+            .unwrap_or_else(|| action.default_binding())
+    }
+
+    /// Bind a key to an action, replacing its current keys.
+    pub fn bind_key(&mut self, action: Action, key: KeyCode) {
+        let mut binding = self.binding(action);
+        binding.keys = vec![key];
+        self.bindings.insert(action, binding);
+    }
+
+    /// Bind a gamepad button to an action, replacing its current buttons.
+    pub fn bind_button(&mut self, action: Action, button: GamepadButton) {
+        let mut binding = self.binding(action);
+        binding.buttons = vec![button];
+        self.bindings.insert(action, binding);
+    }
+}
+
+/// Which stick moves the dog.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum MoveStick {
+    #[default]
+    Left,
+    Right,
+}
+
+impl MoveStick {
+    fn axes(&self) -> (GamepadAxis, GamepadAxis) {
+        match self {
+            MoveStick::Left => (GamepadAxis::LeftStickX, GamepadAxis::LeftStickY),
+            MoveStick::Right => (GamepadAxis::RightStickX, GamepadAxis::RightStickY),
+        }
+    }
+
+    pub fn other(&self) -> MoveStick {
+        match self {
+            MoveStick::Left => MoveStick::Right,
+            MoveStick::Right => MoveStick::Left,
+        }
+    }
+}
+
+/// How gamepad sticks and triggers are read. Saved as part of the settings.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, Resource)]
+#[serde(default)]
+pub struct GamepadConfig {
+    /// Stick input below this is ignored.
+    pub inner_deadzone: f32,
+    /// Stick input above this counts as fully pushed.
+    pub outer_deadzone: f32,
+    /// Exponent applied to stick magnitude. Above 1.0 gives finer control at low speeds.
+    pub response_curve: f32,
+    pub move_stick: MoveStick,
+    /// Flicking the other stick barks.
+    pub bark_with_stick: bool,
+    /// Pulling either trigger barks.
+    pub bark_with_triggers: bool,
+}
+
+impl Default for GamepadConfig {
+    fn default() -> Self {
+        Self {
+            inner_deadzone: 0.15,
+            outer_deadzone: 0.95,
+            response_curve: 1.5,
+            move_stick: MoveStick::Left,
+            bark_with_stick: false,
+            bark_with_triggers: false,
+        }
+    }
+}
+
+impl GamepadConfig {
+    /// Read a stick with a radial deadzone, keeping its direction and remapping its magnitude
+    /// to 0.0..=1.0 with the response curve applied.
+    fn read_stick(&self, gamepad: &Gamepad, stick: MoveStick) -> Vec2 {
+        let (x_axis, y_axis) = stick.axes();
+        let (Some(x), Some(y)) = (gamepad.get(x_axis), gamepad.get(y_axis)) else {
+            return Vec2::ZERO;
+        };
+        let stick = Vec2::new(x, y);
+        let length = stick.length();
+        if length <= self.inner_deadzone {
+            return Vec2::ZERO;
+        }
+        let range = (self.outer_deadzone - self.inner_deadzone).max(f32::EPSILON);
+        let magnitude = ((length - self.inner_deadzone) / range).clamp(0.0, 1.0);
+        stick / length * magnitude.powf(self.response_curve)
+    }
+}
+
+#[derive(Default)]
+struct DeviceActions {
+    pressed: HashSet<Action>,
+    just_pressed: HashSet<Action>,
+}
+
+/// Which actions are pressed this frame, for the keyboard and each gamepad.
+#[derive(Default, Resource)]
+pub struct ActionState {
+    keyboard: DeviceActions,
+    gamepads: HashMap<Entity, DeviceActions>,
+}
+
+impl ActionState {
+    fn devices(&self) -> impl Iterator<Item = &DeviceActions> {
+        std::iter::once(&self.keyboard).chain(self.gamepads.values())
+    }
+
+    fn device(&self, device: InputDevice) -> Option<&DeviceActions> {
+        match device {
+            InputDevice::Keyboard => Some(&self.keyboard),
+            InputDevice::Gamepad(entity) => self.gamepads.get(&entity),
+        }
+    }
+
+    /// Whether the action is held on any device.
+    pub fn pressed(&self, action: Action) -> bool {
+        self.devices().any(|actions| actions.pressed.contains(&action))
+    }
+
+    /// Whether the action started this frame on any device.
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.devices().any(|actions| actions.just_pressed.contains(&action))
+    }
+
+    pub fn device_pressed(&self, device: InputDevice, action: Action) -> bool {
+        self.device(device).is_some_and(|actions| actions.pressed.contains(&action))
+    }
+
+    pub fn device_just_pressed(&self, device: InputDevice, action: Action) -> bool {
+        self.device(device).is_some_and(|actions| actions.just_pressed.contains(&action))
+    }
+
+    /// Every connected device, keyboard first.
+    pub fn input_devices(&self) -> impl Iterator<Item = InputDevice> + '_ {
+        std::iter::once(InputDevice::Keyboard)
+            .chain(self.gamepads.keys().map(|entity| InputDevice::Gamepad(*entity)))
+    }
+}
+
+fn update_action_state(
     keys: Res<ButtonInput<KeyCode>>,
-    gamepads: Query<&Gamepad>,
-    mut player_q: Query<&mut PlayerInput>,
+    gamepads: Query<(Entity, &Gamepad)>,
+    input_map: Res<InputMap>,
+    gamepad_config: Res<GamepadConfig>,
+    mut action_state: ResMut<ActionState>,
+    mut bark_sticks_held: Local<HashSet<Entity>>,
+    mut stick_directions_held: Local<HashMap<Entity, HashSet<Action>>>,
     mut egui_ctx: EguiContexts,
 ) {
-    if player_q.is_empty() {
-        return;
-    }
-
-    let mut movement = Vec2::ZERO;
-    let mut bark = false;
-
-    // Read input from first gamepad.
-    // TODO: Somehow keep track of which gamepad the player is using if there are multiple connected.
-    if let Some(gamepad) = gamepads.iter().next() {
-        // Movement
-        if let (Some(x), Some(y)) = (gamepad.get(GamepadAxis::LeftStickX), gamepad.get(GamepadAxis::LeftStickY)) {
-            let tmp = Vec2::new(x, y);
-            // TODO: See if we can configure the deadzone using Bevy's APIs.
-            if tmp.length() > 0.1 {
-                movement = tmp;
+    let egui_wants_input = egui_ctx.ctx_mut()
+        .map(|ctx| ctx.wants_keyboard_input())
+        .unwrap_or_default();
+
+    let action_state = &mut *action_state;
+    action_state.keyboard = DeviceActions::default();
+    action_state.gamepads.clear();
+
+    for action in Action::ALL {
+        let binding = input_map.binding(action);
+
+        // Keyboard
+        if !egui_wants_input {
+            if keys.any_pressed(binding.keys.iter().copied()) {
+                action_state.keyboard.pressed.insert(action);
+            }
+            if keys.any_just_pressed(binding.keys.iter().copied()) {
+                action_state.keyboard.just_pressed.insert(action);
             }
         }
 
-        // Shoot
-        bark |= gamepad.just_pressed(GamepadButton::South);
+        // Gamepads
+        for (entity, gamepad) in gamepads.iter() {
+            let actions = action_state.gamepads.entry(entity).or_default();
+            if gamepad.any_pressed(binding.buttons.iter().copied()) {
+                actions.pressed.insert(action);
+            }
+            if gamepad.any_just_pressed(binding.buttons.iter().copied()) {
+                actions.just_pressed.insert(action);
+            }
+        }
     }
 
-    // Read input from keyboard.
-    let egui_wants_input = egui_ctx.ctx_mut()
-        .map(|ctx| ctx.wants_keyboard_input())
-        .unwrap_or_default();
-    if !egui_wants_input {
-        // Movement
-        if movement == Vec2::ZERO {
-            let right = keys.any_pressed([KeyCode::KeyD, KeyCode::ArrowRight]);
-            let left = keys.any_pressed([KeyCode::KeyA, KeyCode::ArrowLeft]);
-            let x = (right as i8 - left as i8) as f32;
+    for (entity, gamepad) in gamepads.iter() {
+        let actions = action_state.gamepads.entry(entity).or_default();
 
-            let up = keys.any_pressed([KeyCode::KeyW, KeyCode::ArrowUp]);
-            let down = keys.any_pressed([KeyCode::KeyS, KeyCode::ArrowDown]);
-            let y = (up as i8 - down as i8) as f32;
+        // The move stick also counts as directions, so it can navigate menus.
+        let stick = gamepad_config.read_stick(gamepad, gamepad_config.move_stick);
+        let directions = [
+            (Action::MoveUp, stick.y > STICK_DIRECTION_THRESHOLD),
+            (Action::MoveDown, stick.y < -STICK_DIRECTION_THRESHOLD),
+            (Action::MoveLeft, stick.x < -STICK_DIRECTION_THRESHOLD),
+            (Action::MoveRight, stick.x > STICK_DIRECTION_THRESHOLD),
+        ];
+        let held = stick_directions_held.entry(entity).or_default();
+        for (action, pushed) in directions {
+            if !pushed {
+                held.remove(&action);
+                continue;
+            }
+            actions.pressed.insert(action);
+            if held.insert(action) {
+                actions.just_pressed.insert(action);
+            }
+        }
 
-            movement = Vec2::new(x, y).normalize_or_zero();
+        // Alternative ways to bark on a gamepad.
+        let mut bark = false;
+        if gamepad_config.bark_with_triggers {
+            let triggers = [GamepadButton::LeftTrigger2, GamepadButton::RightTrigger2];
+            bark |= gamepad.any_just_pressed(triggers);
+        }
+        if gamepad_config.bark_with_stick {
+            // Only bark when the stick is first flicked, not while it's held.
+            let stick = gamepad_config.read_stick(gamepad, gamepad_config.move_stick.other());
+            let held = stick.length() > STICK_BARK_THRESHOLD;
+            if held && bark_sticks_held.insert(entity) {
+                bark = true;
+            } else if !held {
+                bark_sticks_held.remove(&entity);
+            }
         }
+        if bark {
+            actions.pressed.insert(Action::Bark);
+            actions.just_pressed.insert(Action::Bark);
+        }
+    }
+}
+
+/// Most players that can join a game.
+pub const MAX_PLAYERS: usize = 4;
 
-        // Bark
-        bark |= keys.just_pressed(KeyCode::Space);
+/// How far the bark stick has to be pushed to bark.
+const STICK_BARK_THRESHOLD: f32 = 0.6;
+/// How far the move stick has to be pushed to count as a direction.
+const STICK_DIRECTION_THRESHOLD: f32 = 0.5;
+
+/// Where a player's input comes from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InputDevice {
+    #[default]
+    Keyboard,
+    Gamepad(Entity),
+}
+
+/// Devices for each player that joined, in join order.
+#[derive(Default, Resource)]
+pub struct Players {
+    pub devices: Vec<InputDevice>,
+}
+
+impl Players {
+    /// Number of dogs to spawn. There's always at least one.
+    pub fn count(&self) -> usize {
+        self.devices.len().max(1)
+    }
+
+    pub fn device(&self, player: usize) -> InputDevice {
+        self.devices.get(player).copied().unwrap_or_default()
+    }
+
+    pub fn has_joined(&self, device: InputDevice) -> bool {
+        self.devices.contains(&device)
     }
 
-    // Store results in player input components.
+    pub fn join(&mut self, device: InputDevice) {
+        if !self.has_joined(device) && self.devices.len() < MAX_PLAYERS {
+            info!("Player {} joined with {:?}", self.devices.len() + 1, device);
+            self.devices.push(device);
+        }
+    }
+
+    pub fn leave(&mut self, device: InputDevice) {
+        self.devices.retain(|d| *d != device);
+    }
+}
+
+#[derive(Default, Component, Reflect)]
+pub struct PlayerInput {
+    pub player: usize,
+    pub movement: Vec2,
+    pub bark: bool,
+}
+
+pub fn read_player_input(
+    gamepads: Query<&Gamepad>,
+    gamepad_config: Res<GamepadConfig>,
+    actions: Res<ActionState>,
+    players: Res<Players>,
+    // The autopilot drives its own dogs.
+    mut player_q: Query<&mut PlayerInput, Without<Autopilot>>,
+) {
     for mut input in player_q.iter_mut() {
+        let device = players.device(input.player);
+
+        // Movement. Sticks keep their magnitude so the dog can sneak up on cats.
+        let mut movement = Vec2::ZERO;
+        if let InputDevice::Gamepad(entity) = device && let Ok(gamepad) = gamepads.get(entity) {
+            movement = gamepad_config.read_stick(gamepad, gamepad_config.move_stick);
+        }
+        // Keys and the D-pad move at full speed.
+        if movement == Vec2::ZERO {
+            let pressed = |action| actions.device_pressed(device, action);
+            let x = (pressed(Action::MoveRight) as i8 - pressed(Action::MoveLeft) as i8) as f32;
+            let y = (pressed(Action::MoveUp) as i8 - pressed(Action::MoveDown) as i8) as f32;
+            movement = Vec2::new(x, y).normalize_or_zero();
+        }
         input.movement = movement;
-        input.bark = bark;
+
+        // Bark. Held until the next gameplay tick uses it, since there may not be one every frame.
+        input.bark |= actions.device_just_pressed(device, Action::Bark);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn players_count_is_at_least_one() {
+        let mut players = Players::default();
+        assert_eq!(players.count(), 1);
+
+        players.join(InputDevice::Keyboard);
+        assert_eq!(players.count(), 1);
+        players.join(InputDevice::Gamepad(Entity::PLACEHOLDER));
+        assert_eq!(players.count(), 2);
+    }
+
+    #[test]
+    fn players_join_once_per_device() {
+        let mut players = Players::default();
+        players.join(InputDevice::Keyboard);
+        players.join(InputDevice::Keyboard);
+        assert_eq!(players.count(), 1);
+
+        players.leave(InputDevice::Keyboard);
+        assert!(players.devices.is_empty());
     }
 }
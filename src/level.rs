@@ -1,16 +1,32 @@
-use std::ops::Deref;
+use std::ops::{Deref, DerefMut};
 
+use avian2d::prelude::Collider;
 use bevy::prelude::*;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    WORLD_SIZE,
+    AppState, WORLD_SIZE,
     assets::GameAssets,
-    cats::{self, CAT_BOUNDS, Cat},
+    cats::{self, CAT_BOUNDS, CAT_COLORS, Cat, CatKind},
     dog::{self, Dog},
+    furniture::{self, FurnitureKind},
     game::{CatBox, GameState},
+    input::Players,
+    physics::{collider, GameLayer},
+    rng::GameRng,
 };
 
+/// Par time for levels that don't set one: a base time plus some time per cat.
+const DEFAULT_PAR_TIME: f32 = 10.0;
+const DEFAULT_PAR_TIME_PER_CAT: f32 = 4.0;
+
+/// Horizontal gap between dogs when there are multiple players.
+const DOG_SPACING: f32 = 24.0;
+/// How far from the middle of the pen random cats have to spawn, so they don't start right next to it.
+const PEN_CLEARANCE: f32 = 80.0;
+/// How many random spots to try for each cat before giving up, in case obstacles cover the room.
+const MAX_SPAWN_ATTEMPTS: usize = 1000;
+
 pub struct LevelPlugin;
 
 impl Plugin for LevelPlugin {
@@ -18,7 +34,13 @@ impl Plugin for LevelPlugin {
         app
             .init_resource::<CurrentLevel>()
             .init_resource::<Levels>()
-            .add_systems(OnEnter(GameState::Playing), spawn_next_level);
+            .init_resource::<LevelReloadSettings>()
+            .add_message::<RespawnLevel>()
+            .add_systems(OnEnter(GameState::Playing), spawn_next_level)
+            .add_systems(Update, (
+                hot_reload_levels.run_if(resource_exists::<GameAssets>),
+                respawn_current_level.run_if(in_state(AppState::Playing)),
+            ).chain());
     }
 }
 
@@ -29,85 +51,363 @@ pub struct LevelCats {
     pub chonk: u8,
 }
 
-#[derive(Debug, Default, Deserialize, Resource, Asset, TypePath)]
-pub struct Levels(Vec<LevelCats>);
+impl LevelCats {
+    pub fn add(&mut self, kind: CatKind) {
+        match kind {
+            CatKind::Basic => self.basic += 1,
+            CatKind::Kitten => self.kitten += 1,
+            CatKind::Chonk => self.chonk += 1,
+        }
+    }
+}
+
+/// The pen cats need to be herded into.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct PenData {
+    pub pos: Vec2,
+    pub size: Vec2,
+}
+
+impl Default for PenData {
+    fn default() -> Self {
+        Self {
+            pos: Vec2::ZERO,
+            size: Vec2::splat(60.0),
+        }
+    }
+}
+
+/// A hand-placed cat.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct CatSpawn {
+    pub kind: CatKind,
+    pub pos: Vec2,
+    /// Index into `CAT_COLORS`. Picks a random color if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<usize>,
+}
+
+/// A piece of furniture that blocks cats and the dog.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ObstacleData {
+    #[serde(default)]
+    pub kind: FurnitureKind,
+    pub pos: Vec2,
+    /// Defaults to a size based on the kind of furniture.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<Vec2>,
+}
+
+impl ObstacleData {
+    pub fn size(&self) -> Vec2 {
+        self.size.unwrap_or_else(|| self.kind.default_size())
+    }
+
+    pub fn rect(&self) -> Rect {
+        Rect::from_center_size(self.pos, self.size())
+    }
+}
+
+/// Everything needed to spawn a level.
+///
+/// Every field is optional so that the old format, which only listed how many of each cat to spawn
+/// at random, is still a valid level.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LevelData {
+    pub name: String,
+    pub pen: PenData,
+    /// Where the dog starts. Defaults to the middle of the pen.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dog_start: Option<Vec2>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub cats: Vec<CatSpawn>,
+    /// Number of each kind of cat to spawn at random locations, in addition to `cats`.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub basic: u8,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub kitten: u8,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub chonk: u8,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub obstacles: Vec<ObstacleData>,
+    /// Seconds to clear the level in to get 3 stars. Defaults to a time based on the number of cats.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub par_time: Option<f32>,
+}
+
+fn is_zero(count: &u8) -> bool {
+    *count == 0
+}
+
+impl LevelData {
+    pub fn dog_start(&self) -> Vec2 {
+        self.dog_start.unwrap_or(self.pen.pos)
+    }
+
+    pub fn num_cats(&self) -> usize {
+        self.cats.len() + self.basic as usize + self.kitten as usize + self.chonk as usize
+    }
+
+    pub fn par_time(&self) -> f32 {
+        self.par_time
+            .unwrap_or_else(|| DEFAULT_PAR_TIME + DEFAULT_PAR_TIME_PER_CAT * self.num_cats() as f32)
+    }
+
+    pub fn random_cats(&self) -> LevelCats {
+        LevelCats {
+            basic: self.basic,
+            kitten: self.kitten,
+            chonk: self.chonk,
+        }
+    }
+
+    /// Total number of each kind of cat in the level, both placed and random.
+    pub fn cat_counts(&self) -> LevelCats {
+        let mut counts = self.random_cats();
+        for spawn in &self.cats {
+            counts.add(spawn.kind);
+        }
+        counts
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Resource, Asset, TypePath)]
+pub struct Levels(Vec<LevelData>);
 
 impl Deref for Levels {
-    type Target = Vec<LevelCats>;
+    type Target = Vec<LevelData>;
 
     fn deref(&self) -> &Self::Target {
         &self.0
     }
 }
 
+impl DerefMut for Levels {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
 #[derive(Default, Resource)]
 pub struct CurrentLevel {
     pub index: usize,
-    pub cats: LevelCats,
+    pub data: LevelData,
     pub cats_herded: u8,
+    /// Level to start at instead of the next one, e.g. when picked from the level select.
+    pub start_index: Option<usize>,
 }
 
+#[derive(Resource)]
+pub struct LevelReloadSettings {
+    /// Respawn the level being played when the levels file changes.
+    pub respawn_current_level: bool,
+}
+
+impl Default for LevelReloadSettings {
+    fn default() -> Self {
+        Self {
+            respawn_current_level: true,
+        }
+    }
+}
+
+/// Marks entities that belong to the current level and get despawned when the next one starts.
+#[derive(Component)]
+pub struct LevelEntity;
+
+/// Despawn and respawn the current level, e.g. after it was edited.
+#[derive(Message)]
+pub struct RespawnLevel;
+
+type LevelEntityFilter = Or<(With<Cat>, With<Dog>, With<CatBox>, With<LevelEntity>)>;
+
 fn spawn_next_level(
     mut commands: Commands,
     assets: Res<GameAssets>,
     levels: Res<Levels>,
+    players: Res<Players>,
+    mut rng: ResMut<GameRng>,
     mut current_level: ResMut<CurrentLevel>,
-    cats_q: Query<Entity, With<Cat>>,
-    dog_q: Query<Entity, With<Dog>>,
-    catbox_q: Query<&Transform, With<CatBox>>,
+    level_q: Query<Entity, LevelEntityFilter>,
 ) {
-    // Despawn all cats.
-    for entity in cats_q.iter() {
-        commands.entity(entity).despawn();
-    }
-
-    // Despawn dog.
-    for entity in dog_q.iter() {
-        commands.entity(entity).despawn();
-    }
+    despawn_level(&mut commands, &level_q);
 
-    // Spawn a new dog.
-    let catbox_pos = catbox_q.single()
-        .map(|t| t.translation.truncate())
-        .unwrap_or_default();
-    commands.spawn(dog::dog(catbox_pos, assets.wizard_dog.clone()))
-        .observe(dog::dog_intersects_cat);
-
-    // Then spawn new cats.
-    let level_index = if current_level.index + 1 < levels.len() {
+    let level_index = if let Some(start_index) = current_level.start_index.take() {
+        start_index
+    } else if current_level.index + 1 < levels.len() {
         current_level.index + 1
     } else {
         1
     };
-    let Some(level_cats) = levels.get(level_index) else {
+    let Some(level) = levels.get(level_index) else {
         *current_level = CurrentLevel::default();
         error!("Could not load level {}. Levels list length: {}", level_index, levels.len());
         return;
     };
+    spawn_level(&mut commands, &assets, &mut rng, level_index, level, players.count());
+
+    // Set CurrentLevel info.
+    current_level.index = level_index;
+    current_level.data = level.clone();
+    current_level.cats_herded = 0;
+}
 
-    // Spawn cats in random locations.
-    let random_location = || {
-        loop {
-            let x = (fastrand::f32() - 0.5) * (WORLD_SIZE.x as f32 - (CAT_BOUNDS * 2.0));
-            let y = (fastrand::f32() - 0.5) * (WORLD_SIZE.y as f32 - (CAT_BOUNDS * 2.0));
-            let pos = Vec2::new(x, y);
-            if pos.distance_squared(catbox_pos) > 80.0 * 80.0 {
-                break pos;
+fn hot_reload_levels(
+    mut asset_events: MessageReader<AssetEvent<Levels>>,
+    level_assets: Res<Assets<Levels>>,
+    assets: Res<GameAssets>,
+    settings: Res<LevelReloadSettings>,
+    game_state: Res<State<GameState>>,
+    mut levels: ResMut<Levels>,
+    mut respawn: MessageWriter<RespawnLevel>,
+) {
+    let modified = asset_events.read()
+        .any(|e| e.is_modified(&assets.levels));
+    if !modified {
+        return;
+    }
+    let Some(loaded_levels) = level_assets.get(&assets.levels) else {
+        return;
+    };
+
+    info!("Reloaded levels");
+    *levels = loaded_levels.clone();
+    if settings.respawn_current_level && *game_state.get() == GameState::Playing {
+        respawn.write(RespawnLevel);
+    }
+}
+
+fn respawn_current_level(
+    mut respawn_messages: MessageReader<RespawnLevel>,
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    levels: Res<Levels>,
+    players: Res<Players>,
+    mut rng: ResMut<GameRng>,
+    mut current_level: ResMut<CurrentLevel>,
+    level_q: Query<Entity, LevelEntityFilter>,
+) {
+    if respawn_messages.read().last().is_none() {
+        return;
+    }
+
+    let Some(level) = levels.get(current_level.index) else {
+        error!("Could not respawn level {}. Levels list length: {}", current_level.index, levels.len());
+        return;
+    };
+    despawn_level(&mut commands, &level_q);
+    spawn_level(&mut commands, &assets, &mut rng, current_level.index, level, players.count());
+
+    current_level.data = level.clone();
+    current_level.cats_herded = 0;
+}
+
+fn despawn_level(commands: &mut Commands, level_q: &Query<Entity, LevelEntityFilter>) {
+    for entity in level_q.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn spawn_level(commands: &mut Commands, assets: &GameAssets, rng: &mut GameRng, level_index: usize, level: &LevelData, num_players: usize) {
+    debug!("Spawning level {}: {}", level_index, level.name);
+    rng.start_level(level_index);
+
+    // Spawn cat_box.
+    let catbox_pos = level.pen.pos;
+    commands.spawn((
+        CatBox {
+            size: level.pen.size,
+        },
+        Name::new("CatBox"),
+        Sprite {
+            image: assets.cat_box.clone(),
+            custom_size: Some(level.pen.size),
+            ..default()
+        },
+        Transform::from_translation(catbox_pos.extend(-0.5)),
+        collider(Collider::rectangle(level.pen.size.x, level.pen.size.y), GameLayer::CatBox, GameLayer::Cat),
+    ));
+
+    // Spawn obstacles.
+    for obstacle in &level.obstacles {
+        commands.spawn((
+            LevelEntity,
+            furniture::furniture(obstacle.kind, obstacle.pos, obstacle.size()),
+        ));
+    }
+
+    // Spawn a dog for each player, side by side around the start.
+    for player in 0..num_players {
+        let offset = (player as f32 - (num_players - 1) as f32 / 2.0) * DOG_SPACING;
+        let pos = level.dog_start() + Vec2::new(offset, 0.0);
+        commands.spawn(dog::dog(pos, player, assets.wizard_dog.clone()))
+            .observe(dog::dog_intersects_cat);
+    }
+
+    // Then spawn hand-placed cats.
+    for spawn in &level.cats {
+        let color = spawn.color.map(|i| {
+            let color = CAT_COLORS.get(i).unwrap_or_else(|| {
+                warn!("Invalid cat color index {} in level {}", i, level_index);
+                &CAT_COLORS[0]
+            });
+            Color::srgb_from_array(*color)
+        });
+        commands.spawn(cats::cat(spawn.kind, spawn.pos, color, &mut rng.level, assets));
+    }
+
+    // And fill in the rest in random locations.
+    let pen_margin = (PEN_CLEARANCE - level.pen.size.min_element() / 2.0).max(CAT_BOUNDS);
+    let pen_rect = Rect::from_center_size(catbox_pos, level.pen.size).inflate(pen_margin);
+    let random_location = |rng: &mut fastrand::Rng| {
+        let mut pos = Vec2::ZERO;
+        for _ in 0..MAX_SPAWN_ATTEMPTS {
+            let x = (rng.f32() - 0.5) * (WORLD_SIZE.x as f32 - (CAT_BOUNDS * 2.0));
+            let y = (rng.f32() - 0.5) * (WORLD_SIZE.y as f32 - (CAT_BOUNDS * 2.0));
+            pos = Vec2::new(x, y);
+            let in_obstacle = level.obstacles.iter()
+                .any(|obstacle| obstacle.rect().inflate(CAT_BOUNDS).contains(pos));
+            if !pen_rect.contains(pos) && !in_obstacle {
+                return pos;
             }
         }
+        error!("Could not find a free spot for a cat in level {}, so it may spawn inside something", level_index);
+        pos
     };
-    for _ in 0..level_cats.basic {
-        commands.spawn(cats::basic_cat(random_location(), assets.basic_cat.clone()));
+    let random_cats = level.random_cats();
+    for _ in 0..random_cats.basic {
+        let pos = random_location(&mut rng.level);
+        commands.spawn(cats::cat(CatKind::Basic, pos, None, &mut rng.level, assets));
     }
-    for _ in 0..level_cats.kitten {
-        commands.spawn(cats::kitten_cat(random_location(), assets.kitten.clone()));
+    for _ in 0..random_cats.kitten {
+        let pos = random_location(&mut rng.level);
+        commands.spawn(cats::cat(CatKind::Kitten, pos, None, &mut rng.level, assets));
     }
-    for _ in 0..level_cats.chonk {
-        commands.spawn(cats::chonk_cat(random_location(), assets.fat_cat.clone()));
+    for _ in 0..random_cats.chonk {
+        let pos = random_location(&mut rng.level);
+        commands.spawn(cats::cat(CatKind::Chonk, pos, None, &mut rng.level, assets));
     }
+}
 
-    // Set CurrentLevel info.
-    current_level.index = level_index;
-    current_level.cats = level_cats.clone();
-    current_level.cats_herded = 0;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn old_level_format_still_loads() {
+        let levels: Levels = ron::from_str("([
+            ( basic: 1, kitten: 1, chonk: 1 ),
+            ( basic: 2, kitten: 0, chonk: 0 ),
+        ])").unwrap();
+
+        assert_eq!(levels.len(), 2);
+        let level = &levels[0];
+        assert_eq!(level.pen, PenData::default());
+        assert!(level.cats.is_empty());
+        assert!(level.obstacles.is_empty());
+        let cats = level.random_cats();
+        assert_eq!((cats.basic, cats.kitten, cats.chonk), (1, 1, 1));
+        assert_eq!(levels[1].random_cats().basic, 2);
+    }
 }
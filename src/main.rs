@@ -2,20 +2,30 @@
 
 use bevy::prelude::*;
 use bevy::diagnostic::FrameTimeDiagnosticsPlugin;
-use bevy::window::{WindowMode, WindowResolution};
+use bevy::window::WindowResolution;
 use bevy_kira_audio::AudioPlugin;
 
 mod assets;
+mod audio;
+mod autopilot;
 mod utils;
 mod camera;
 mod cats;
 mod debug;
 mod dog;
+mod editor;
+mod furniture;
 mod game;
+#[cfg(not(target_arch = "wasm32"))]
+mod headless;
 mod input;
 mod level;
 mod log;
 mod physics;
+mod replay;
+mod rng;
+mod save;
+mod score;
 mod ui;
 mod window;
 
@@ -28,19 +38,53 @@ const ALLOW_EXIT: bool = cfg!(not(target_arch = "wasm32"));
 pub enum AppState {
     #[default]
     Loading,
+    LoadingError,
     StartMenu,
+    LevelSelect,
     Credits,
     HowToPlay,
+    Join,
+    Controls,
+    Options,
     Playing,
 }
 
-fn main() {
+fn main() -> AppExit {
     // When building for WASM, print panics to the browser console.
     #[cfg(target_arch = "wasm32")]
     console_error_panic_hook::set_once();
 
-    // TODO: Try to initialize logging before this. Maybe we can also make this code run in a plugin.
-    let saved_window_state = window::load_window_state();
+    let replay = arg_value("--replay")
+        .map(|path| replay::load_replay(&path).map_err(|e| format!("Could not load replay {}: {}", path, e)))
+        .transpose();
+    let replay = match replay {
+        Ok(replay) => replay,
+        Err(e) => return arg_error(&e),
+    };
+    // Replays need the seed they were recorded with.
+    let seed = match parse_arg("--seed") {
+        Ok(seed) => replay.as_ref().map(|replay| replay.seed).or(seed),
+        Err(e) => return arg_error(&e),
+    };
+    // Replays bring their own inputs.
+    let autopilot = has_arg("--autopilot") && replay.is_none();
+
+    // Simulate levels without a window, e.g. in CI.
+    #[cfg(not(target_arch = "wasm32"))]
+    if has_arg("--headless") {
+        return match headless_options(seed, autopilot, replay) {
+            Ok(options) => headless::run(options),
+            Err(e) => arg_error(&e),
+        };
+    }
+
+    // Logging isn't set up yet, so the save plugin logs any problems once it is.
+    let mut save_warnings = Vec::new();
+    let save_data = save::load_save_data(&mut save_warnings);
+    let saved_window_state = save_data.settings.window.clone();
+    let saved_input_map = save_data.settings.controls.clone();
+    let saved_gamepad_config = save_data.settings.gamepad.clone();
+    let saved_audio_settings = save_data.settings.audio.clone();
 
     // Configure DefaultPlugins.
     let default_plugins = DefaultPlugins
@@ -56,7 +100,7 @@ fn main() {
                 )
                 .with_scale_factor_override(1.0),
                 resizable: true,
-                mode: WindowMode::Windowed,
+                mode: window::window_mode(saved_window_state.fullscreen),
                 ..default()
             }),
             ..default()
@@ -83,9 +127,15 @@ fn main() {
         .init_state::<AppState>()
         .add_plugins((
             window::WindowPlugin::new(saved_window_state),
+            save::SavePlugin::new(save_data, save_warnings),
             debug::DebugPlugin,
-            input::InputPlugin,
+            input::InputPlugin::new(saved_input_map, saved_gamepad_config),
+            audio::AudioPlugin::new(saved_audio_settings),
             physics::PhysicsPlugin,
+            rng::RngPlugin::new(seed),
+            replay::ReplayPlugin::new(replay),
+        ))
+        .add_plugins((
             utils::UtilsPlugin,
             assets::AssetsPlugin,
             camera::CameraPlugin,
@@ -93,11 +143,49 @@ fn main() {
             game::GamePlugin,
             level::LevelPlugin,
             cats::CatsPlugin,
+            autopilot::AutopilotPlugin::new(autopilot),
         ));
 
     if ALLOW_EXIT {
         // app.add_systems(Update, bevy::window::close_on_esc);
     }
 
-    app.run();
+    app.run()
+}
+
+/// Whether a command line flag was given, like `--headless`.
+fn has_arg(name: &str) -> bool {
+    std::env::args().skip(1).any(|arg| arg == name)
+}
+
+/// Get the value following a command line option, like `--seed 1234`.
+fn arg_value(name: &str) -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    args.find(|arg| arg == name)?;
+    args.next()
+}
+
+/// Parse the value following a command line option, like `--level 3`.
+fn parse_arg<T: std::str::FromStr>(name: &str) -> Result<Option<T>, String> {
+    arg_value(name)
+        .map(|value| value.parse().map_err(|_| format!("{} should be a number, got {}", name, value)))
+        .transpose()
+}
+
+/// Report a bad command line argument. Logging isn't set up yet.
+fn arg_error(error: &str) -> AppExit {
+    eprintln!("{}", error);
+    AppExit::error()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn headless_options(seed: Option<u64>, autopilot: bool, replay: Option<replay::Replay>) -> Result<headless::HeadlessOptions, String> {
+    Ok(headless::HeadlessOptions {
+        seed,
+        level: parse_arg("--level")?,
+        players: parse_arg("--players")?.unwrap_or(1),
+        max_time: parse_arg("--max-time")?.unwrap_or(headless::DEFAULT_MAX_TIME),
+        autopilot,
+        replay,
+    })
 }
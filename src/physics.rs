@@ -3,6 +3,8 @@ use std::ops::{Deref, DerefMut};
 use bevy::prelude::*;
 use avian2d::prelude::*;
 
+use crate::game::GameSet;
+
 #[derive(PhysicsLayer, Default)]
 pub enum GameLayer {
     #[default]
@@ -10,6 +12,7 @@ pub enum GameLayer {
     CatBox,
     Dog,
     Cat,
+    Obstacle,
 }
 
 pub struct PhysicsPlugin;
@@ -18,11 +21,17 @@ impl Plugin for PhysicsPlugin {
     fn build(&self, app: &mut App) {
         app
             .add_plugins(PhysicsPlugins::default().with_length_unit(32.0))
-            .add_systems(Update, update_movement);
+            .add_systems(FixedFirst, store_previous_positions)
+            .add_systems(FixedUpdate, update_movement.in_set(GameSet::Movement))
+            .add_systems(RunFixedMainLoop, (
+                restore_simulated_positions.in_set(RunFixedMainLoopSystems::BeforeFixedMainLoop),
+                interpolate_positions.in_set(RunFixedMainLoopSystems::AfterFixedMainLoop),
+            ));
     }
 }
 
 #[derive(Clone, Copy, Default, Component)]
+#[require(Interpolated)]
 pub struct Velocity {
     pub inner: Vec2,
 }
@@ -49,6 +58,34 @@ impl Velocity {
     }
 }
 
+/// Smooths out movement between gameplay ticks. Gameplay sees the simulated position in
+/// `Transform` during `FixedUpdate`, and everything else sees it blended between the last two ticks.
+#[derive(Clone, Copy, Default, Component)]
+pub struct Interpolated {
+    previous: Vec2,
+    current: Vec2,
+    /// The position last shown, to notice when something else moves the entity.
+    rendered: Option<Vec2>,
+}
+
+/// A solid rectangle that blocks anything with a `Solid` component.
+#[derive(Component)]
+pub struct Obstacle {
+    pub half_size: Vec2,
+}
+
+impl Obstacle {
+    pub fn rect(&self, pos: Vec2) -> Rect {
+        Rect::from_center_half_size(pos, self.half_size)
+    }
+}
+
+/// Gives an entity a box that can't move through `Obstacle`s.
+#[derive(Component)]
+pub struct Solid {
+    pub half_size: Vec2,
+}
+
 // TODO: Consider implementing a new method that enforces min and max.
 #[derive(Component)]
 pub struct MovementBounds {
@@ -58,10 +95,25 @@ pub struct MovementBounds {
 
 pub fn update_movement(
     time: Res<Time>,
-    mut movement_q: Query<(&Velocity, &mut Transform, Option<&MovementBounds>)>,
+    mut movement_q: Query<(&Velocity, &mut Transform, Option<&MovementBounds>, Option<&Solid>), Without<Obstacle>>,
+    obstacle_q: Query<(&Obstacle, &Transform)>,
 ) {
-    for (velocity, mut transform, bounds) in movement_q.iter_mut() {
-        transform.translation += velocity.inner.extend(0.0) * time.delta_secs();
+    let obstacles: Vec<Rect> = obstacle_q.iter()
+        .map(|(obstacle, transform)| obstacle.rect(transform.translation.truncate()))
+        .collect();
+    for (velocity, mut transform, bounds, solid) in movement_q.iter_mut() {
+        let delta = velocity.inner * time.delta_secs();
+        if let Some(solid) = solid {
+            // Move one axis at a time so we slide along the edges of obstacles.
+            let mut pos = transform.translation.truncate();
+            pos.x += delta.x;
+            push_out_of_obstacles(&mut pos, solid.half_size, &obstacles, Vec2::new(delta.x, 0.0));
+            pos.y += delta.y;
+            push_out_of_obstacles(&mut pos, solid.half_size, &obstacles, Vec2::new(0.0, delta.y));
+            transform.translation = pos.extend(transform.translation.z);
+        } else {
+            transform.translation += delta.extend(0.0);
+        }
         // Take into account level bounds and clamp to them.
         // TODO: Move bounds clamping to a different system that uses change detection of transform.
         if let Some(bounds) = bounds {
@@ -71,6 +123,67 @@ pub fn update_movement(
     }
 }
 
+fn restore_simulated_positions(
+    mut interp_q: Query<(&mut Interpolated, &mut Transform)>,
+) {
+    for (mut interp, mut transform) in interp_q.iter_mut() {
+        let pos = transform.translation.truncate();
+        if interp.rendered == Some(pos) {
+            transform.translation = interp.current.extend(transform.translation.z);
+        } else {
+            // Just spawned or moved outside of gameplay, so jump straight there.
+            interp.previous = pos;
+            interp.current = pos;
+        }
+    }
+}
+
+fn store_previous_positions(
+    mut interp_q: Query<(&mut Interpolated, &Transform)>,
+) {
+    for (mut interp, transform) in interp_q.iter_mut() {
+        interp.previous = transform.translation.truncate();
+    }
+}
+
+fn interpolate_positions(
+    time: Res<Time<Fixed>>,
+    mut interp_q: Query<(&mut Interpolated, &mut Transform)>,
+) {
+    let t = time.overstep_fraction();
+    for (mut interp, mut transform) in interp_q.iter_mut() {
+        interp.current = transform.translation.truncate();
+        if interp.rendered.is_none() {
+            // Spawned during gameplay, so there's nothing to blend from yet.
+            interp.previous = interp.current;
+        }
+        let pos = interp.previous.lerp(interp.current, t);
+        transform.translation = pos.extend(transform.translation.z);
+        interp.rendered = Some(pos);
+    }
+}
+
+fn push_out_of_obstacles(pos: &mut Vec2, half_size: Vec2, obstacles: &[Rect], delta: Vec2) {
+    for obstacle in obstacles {
+        // Grow the obstacle by our size so we only need to check our center point.
+        let rect = Rect::from_center_half_size(obstacle.center(), obstacle.half_size() + half_size);
+        if pos.x <= rect.min.x || pos.x >= rect.max.x || pos.y <= rect.min.y || pos.y >= rect.max.y {
+            continue;
+        }
+
+        // Push back out the way we came in.
+        if delta.x > 0.0 {
+            pos.x = rect.min.x;
+        } else if delta.x < 0.0 {
+            pos.x = rect.max.x;
+        } else if delta.y > 0.0 {
+            pos.y = rect.min.y;
+        } else if delta.y < 0.0 {
+            pos.y = rect.max.y;
+        }
+    }
+}
+
 pub fn collider(
     collider: Collider,
     memberships: impl Into<LayerMask>,
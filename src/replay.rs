@@ -0,0 +1,286 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AppState,
+    autopilot::Demo,
+    game::{DEFAULT_TICK_RATE, GameSet, GameState},
+    input::{InputDevice, PlayerInput, Players},
+    level::{CurrentLevel, LevelData, Levels, RespawnLevel},
+    rng::GameRng,
+    save,
+};
+
+/// Storage key for the replay of the most recent level attempt.
+const LAST_REPLAY_KEY: &str = "last_replay";
+
+pub struct ReplayPlugin {
+    replay: Option<Replay>,
+}
+
+impl ReplayPlugin {
+    /// Play back the given replay instead of recording.
+    pub fn new(replay: Option<Replay>) -> Self {
+        Self {
+            replay,
+        }
+    }
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Recorder>();
+        if let Some(replay) = &self.replay {
+            app
+                .insert_resource(Playback {
+                    replay: replay.clone(),
+                    started: false,
+                    run: 0,
+                    tick_in_run: 0,
+                })
+                .add_systems(OnEnter(AppState::StartMenu), start_playback)
+                .add_systems(FixedUpdate, play_back_inputs.in_set(GameSet::Input));
+        } else {
+            app
+                .add_systems(OnEnter(GameState::Playing), start_recording)
+                .add_systems(OnExit(GameState::Playing), save_last_replay)
+                .add_systems(Update, (save_last_replay, start_recording).chain().run_if(on_message::<RespawnLevel>))
+                // Demos aren't recorded, so they don't replace the last replay.
+                .add_systems(FixedUpdate, record_inputs.in_set(GameSet::Input).run_if(not(resource_exists::<Demo>)));
+        }
+    }
+}
+
+/// Everything needed to play a level attempt again exactly as it happened.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Replay {
+    pub seed: u64,
+    pub level_index: usize,
+    /// The level as it was when recorded, so edits to the levels file since then don't change the replay.
+    pub level: Option<LevelData>,
+    pub num_players: usize,
+    pub tick_rate: f64,
+    /// Inputs for every tick, stored as runs of identical ticks to keep files small.
+    pub runs: Vec<InputRun>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct InputRun {
+    pub ticks: u32,
+    /// One per player.
+    pub inputs: Vec<TickInput>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct TickInput {
+    pub movement: (f32, f32),
+    pub bark: bool,
+}
+
+impl Default for Replay {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            level_index: 0,
+            level: None,
+            num_players: 1,
+            tick_rate: DEFAULT_TICK_RATE,
+            runs: Vec::new(),
+        }
+    }
+}
+
+impl Replay {
+    fn push(&mut self, inputs: Vec<TickInput>) {
+        match self.runs.last_mut() {
+            Some(run) if run.inputs == inputs => run.ticks += 1,
+            _ => self.runs.push(InputRun {
+                ticks: 1,
+                inputs,
+            }),
+        }
+    }
+
+    /// Put the level the replay was recorded on back into `levels`, in case it was edited since.
+    /// Returns a warning if the replay may not play back the same.
+    pub fn restore_level(&self, levels: &mut Levels) -> Option<String> {
+        let Some(level) = &self.level else {
+            return Some(format!("Replay doesn't include level {}, so it won't play back the same if it was edited", self.level_index));
+        };
+        let Some(current) = levels.get_mut(self.level_index) else {
+            return Some(format!("Level {} doesn't exist anymore, there are {} levels", self.level_index, levels.len()));
+        };
+        if current == level {
+            return None;
+        }
+        *current = level.clone();
+        Some(format!("Level {} was edited since the replay was recorded, so playing back the recorded version", self.level_index))
+    }
+}
+
+/// Read a replay file, e.g. one attached to a bug report.
+pub fn load_replay(path: &str) -> Result<Replay, String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let replay: Replay = ron::from_str(&contents).map_err(|e| e.to_string())?;
+    if !(replay.tick_rate.is_finite() && replay.tick_rate > 0.0) {
+        return Err(format!("Invalid tick rate {}", replay.tick_rate));
+    }
+    Ok(replay)
+}
+
+/// Inputs recorded so far in the current level attempt.
+#[derive(Default, Resource)]
+pub struct Recorder {
+    pub replay: Replay,
+}
+
+#[derive(Resource)]
+struct Playback {
+    replay: Replay,
+    started: bool,
+    run: usize,
+    tick_in_run: u32,
+}
+
+fn start_recording(
+    mut recorder: ResMut<Recorder>,
+) {
+    recorder.replay = Replay::default();
+}
+
+pub fn record_inputs(
+    mut recorder: ResMut<Recorder>,
+    rng: Res<GameRng>,
+    current_level: Res<CurrentLevel>,
+    players: Res<Players>,
+    fixed_time: Res<Time<Fixed>>,
+    input_q: Query<&PlayerInput>,
+) {
+    // Note how the attempt started on its first tick, once the level has spawned.
+    if recorder.replay.runs.is_empty() {
+        let replay = &mut recorder.replay;
+        replay.seed = rng.seed();
+        replay.level_index = current_level.index;
+        replay.level = Some(current_level.data.clone());
+        replay.num_players = players.count();
+        replay.tick_rate = 1.0 / fixed_time.timestep().as_secs_f64();
+    }
+
+    let mut inputs: Vec<&PlayerInput> = input_q.iter().collect();
+    inputs.sort_by_key(|input| input.player);
+    let inputs = inputs.into_iter()
+        .map(|input| TickInput {
+            movement: input.movement.into(),
+            bark: input.bark,
+        })
+        .collect();
+    recorder.replay.push(inputs);
+}
+
+/// Save the attempt so far. Also available from the debug menu for saving mid-level.
+pub fn save_last_replay(
+    recorder: Res<Recorder>,
+) {
+    if recorder.replay.runs.is_empty() {
+        return;
+    }
+    info!("Saving replay of level {}", recorder.replay.level_index);
+    save::save(LAST_REPLAY_KEY, &recorder.replay);
+}
+
+/// Skip the menus and go straight to the replayed level.
+fn start_playback(
+    mut playback: ResMut<Playback>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut levels: ResMut<Levels>,
+    mut players: ResMut<Players>,
+    mut fixed_time: ResMut<Time<Fixed>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if playback.started {
+        return;
+    }
+    playback.started = true;
+
+    let replay = &playback.replay;
+    info!("Playing back replay of level {} with seed {}", replay.level_index, replay.seed);
+    if let Some(warning) = replay.restore_level(&mut levels) {
+        warn!("{}", warning);
+    }
+    current_level.start_index = Some(replay.level_index);
+    // Inputs come from the replay, so the devices don't matter.
+    players.devices = vec![InputDevice::Keyboard; replay.num_players.max(1)];
+    fixed_time.set_timestep_hz(replay.tick_rate);
+    next_state.set(AppState::Playing);
+}
+
+fn play_back_inputs(
+    mut playback: ResMut<Playback>,
+    mut input_q: Query<&mut PlayerInput>,
+) {
+    let playback = &mut *playback;
+    let Some(run) = playback.replay.runs.get(playback.run) else {
+        // Out of inputs, so let the dogs rest.
+        for mut input in input_q.iter_mut() {
+            input.movement = Vec2::ZERO;
+            input.bark = false;
+        }
+        return;
+    };
+
+    for mut input in input_q.iter_mut() {
+        let tick = run.inputs.get(input.player).copied().unwrap_or_default();
+        input.movement = tick.movement.into();
+        input.bark = tick.bark;
+    }
+
+    playback.tick_in_run += 1;
+    if playback.tick_in_run >= run.ticks {
+        playback.tick_in_run = 0;
+        playback.run += 1;
+        if playback.run == playback.replay.runs.len() {
+            info!("Replay finished");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tick(x: f32, bark: bool) -> Vec<TickInput> {
+        vec![TickInput {
+            movement: (x, 0.0),
+            bark,
+        }]
+    }
+
+    #[test]
+    fn push_merges_identical_ticks_into_runs() {
+        let mut replay = Replay::default();
+        replay.push(tick(1.0, false));
+        replay.push(tick(1.0, false));
+        replay.push(tick(1.0, true));
+        replay.push(tick(0.0, false));
+        replay.push(tick(0.0, false));
+        replay.push(tick(0.0, false));
+
+        let runs: Vec<(u32, Vec<TickInput>)> = replay.runs.into_iter()
+            .map(|run| (run.ticks, run.inputs))
+            .collect();
+        assert_eq!(runs, vec![
+            (2, tick(1.0, false)),
+            (1, tick(1.0, true)),
+            (3, tick(0.0, false)),
+        ]);
+    }
+
+    #[test]
+    fn push_starts_a_new_run_when_players_change() {
+        let mut replay = Replay::default();
+        replay.push(tick(0.0, false));
+        replay.push([tick(0.0, false), tick(0.0, false)].concat());
+        assert_eq!(replay.runs.len(), 2);
+    }
+}
@@ -0,0 +1,98 @@
+use bevy::prelude::*;
+use fastrand::Rng;
+
+pub struct RngPlugin {
+    seed: Option<u64>,
+}
+
+impl RngPlugin {
+    /// Use the given seed, or pick one at random.
+    pub fn new(seed: Option<u64>) -> Self {
+        Self {
+            seed,
+        }
+    }
+}
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        let seed = self.seed.unwrap_or_else(|| fastrand::u64(..));
+        info!("Using random seed {}", seed);
+        app.insert_resource(GameRng::new(seed));
+    }
+}
+
+/// All gameplay randomness, derived from one seed so a run can be reproduced with `--seed`.
+/// Each subsystem draws from its own stream so that, e.g., cosmetic effects don't change where cats go.
+#[derive(Resource)]
+pub struct GameRng {
+    seed: u64,
+    /// Spawning levels and the cats in them.
+    pub level: Rng,
+    /// Cat behavior while playing.
+    pub cats: Rng,
+    /// Purely visual effects.
+    pub effects: Rng,
+    /// Picking which sounds to play.
+    pub audio: Rng,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self::for_level(seed, 0)
+    }
+
+    /// Streams for the given level, derived from the seed.
+    fn for_level(seed: u64, level_index: usize) -> Self {
+        let mut root = Rng::with_seed(seed ^ (level_index as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        Self {
+            seed,
+            level: root.fork(),
+            cats: root.fork(),
+            effects: root.fork(),
+            audio: root.fork(),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Restart every stream for the given level, so it plays out the same no matter what came before.
+    pub fn start_level(&mut self, level_index: usize) {
+        *self = Self::for_level(self.seed, level_index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draw(rng: &mut GameRng) -> [u64; 4] {
+        [rng.level.u64(..), rng.cats.u64(..), rng.effects.u64(..), rng.audio.u64(..)]
+    }
+
+    #[test]
+    fn start_level_gives_the_same_streams_regardless_of_history() {
+        let mut a = GameRng::new(1234);
+        let mut b = GameRng::new(1234);
+        // Use the streams differently beforehand, like playing another level first.
+        draw(&mut a);
+        b.start_level(2);
+        draw(&mut b);
+        draw(&mut b);
+
+        a.start_level(3);
+        b.start_level(3);
+        assert_eq!(draw(&mut a), draw(&mut b));
+    }
+
+    #[test]
+    fn levels_get_different_streams() {
+        let mut a = GameRng::new(1234);
+        let mut b = GameRng::new(1234);
+        a.start_level(1);
+        b.start_level(2);
+        assert_ne!(draw(&mut a), draw(&mut b));
+    }
+}
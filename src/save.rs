@@ -0,0 +1,304 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use bevy::app::AppExit;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    AppState,
+    audio::AudioSettings,
+    autopilot::Demo,
+    game::{CatStats, GameState},
+    input::{GamepadConfig, InputMap},
+    score::{self, LevelResult},
+    window::WindowState,
+};
+
+const SAVE_DATA_KEY: &str = "save_data";
+
+pub struct SavePlugin {
+    save_data: SaveData,
+    /// Problems from loading the save data, which happens before logging is set up.
+    load_warnings: Vec<String>,
+}
+
+impl SavePlugin {
+    pub fn new(save_data: SaveData, load_warnings: Vec<String>) -> Self {
+        Self {
+            save_data,
+            load_warnings,
+        }
+    }
+}
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        let load_warnings = self.load_warnings.clone();
+        app
+            .insert_resource(self.save_data.clone())
+            .add_systems(Startup, move || {
+                for warning in load_warnings.iter() {
+                    warn!("{}", warning);
+                }
+            })
+            // Demos don't count towards progress.
+            .add_systems(OnEnter(GameState::LevelClear), record_level_result.after(score::compute_level_result).run_if(not(resource_exists::<Demo>)))
+            .add_systems(OnEnter(GameState::Victory), record_level_result.after(score::compute_level_result).run_if(not(resource_exists::<Demo>)))
+            .add_systems(OnExit(AppState::Controls), save_settings)
+            .add_systems(OnExit(AppState::Options), save_settings)
+            .add_systems(Last, save_on_exit.run_if(on_message::<AppExit>));
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, Resource)]
+#[serde(default)]
+pub struct SaveData {
+    /// Index of the furthest level the player can start from.
+    pub highest_unlocked_level: usize,
+    /// Best results, keyed by level index.
+    pub best_results: BTreeMap<usize, BestResult>,
+    pub total_cats_herded: u32,
+    pub settings: Settings,
+}
+
+impl Default for SaveData {
+    fn default() -> Self {
+        Self {
+            // Level 0 is only for testing, so start at 1.
+            highest_unlocked_level: 1,
+            best_results: default(),
+            total_cats_herded: 0,
+            settings: default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct BestResult {
+    /// Best time in seconds.
+    pub time: f32,
+    pub score: u32,
+    pub stars: u8,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Settings {
+    pub window: WindowState,
+    pub controls: InputMap,
+    pub gamepad: GamepadConfig,
+    pub audio: AudioSettings,
+}
+
+/// Load the save data. Runs before logging is set up, so any problems are added to `warnings` to log later.
+pub fn load_save_data(warnings: &mut Vec<String>) -> SaveData {
+    #[cfg_attr(target_arch = "wasm32", allow(unused_mut))]
+    let mut save_data = load(SAVE_DATA_KEY, warnings);
+    #[cfg(not(target_arch = "wasm32"))]
+    migrate_window_state(&mut save_data, warnings);
+    save_data
+}
+
+/// Older versions kept the window state in its own file in the working directory.
+/// Fold it into the save data the first time we find it.
+#[cfg(not(target_arch = "wasm32"))]
+fn migrate_window_state(save_data: &mut SaveData, warnings: &mut Vec<String>) {
+    use std::{fs, path::Path};
+
+    const WINDOW_STATE_FILENAME: &str = "window_state.ron";
+
+    let path = Path::new(WINDOW_STATE_FILENAME);
+    if !path.is_file() {
+        return;
+    }
+    let window_state = fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|contents| ron::from_str(&contents).map_err(|e| e.to_string()));
+    match window_state {
+        Ok(window_state) => save_data.settings.window = window_state,
+        Err(e) => warnings.push(format!("Could not read {}, ignoring it: {}", WINDOW_STATE_FILENAME, e)),
+    }
+    // Only remove the old file once the save data has it, so it isn't lost.
+    match try_save(SAVE_DATA_KEY, &*save_data) {
+        Ok(()) => {
+            if let Err(e) = fs::remove_file(path) {
+                warnings.push(format!("Could not remove {}: {}", WINDOW_STATE_FILENAME, e));
+            }
+        }
+        Err(e) => warnings.push(format!("Could not save {}: {}", SAVE_DATA_KEY, e)),
+    }
+}
+
+fn record_level_result(
+    mut save_data: ResMut<SaveData>,
+    window_state: Res<WindowState>,
+    input_map: Res<InputMap>,
+    gamepad_config: Res<GamepadConfig>,
+    audio_settings: Res<AudioSettings>,
+    result: Res<LevelResult>,
+    cat_stats: Res<CatStats>,
+) {
+    // Skipping a level with the debug key doesn't count as clearing it.
+    if !cat_stats.all_penned() {
+        debug!("Level {} was skipped, not recording its result", result.level_index);
+        return;
+    }
+    let time = result.time.as_secs_f32();
+    save_data.best_results.entry(result.level_index)
+        .and_modify(|best| {
+            best.time = best.time.min(time);
+            best.score = best.score.max(result.score);
+            best.stars = best.stars.max(result.stars);
+        })
+        .or_insert(BestResult {
+            time,
+            score: result.score,
+            stars: result.stars,
+        });
+    save_data.highest_unlocked_level = save_data.highest_unlocked_level.max(result.level_index + 1);
+    save_data.total_cats_herded += cat_stats.in_pen();
+    save_data.settings.window = window_state.clone();
+    save_data.settings.controls = input_map.clone();
+    save_data.settings.gamepad = gamepad_config.clone();
+    save_data.settings.audio = audio_settings.clone();
+
+    save(SAVE_DATA_KEY, &*save_data);
+}
+
+fn save_settings(
+    mut save_data: ResMut<SaveData>,
+    window_state: Res<WindowState>,
+    input_map: Res<InputMap>,
+    gamepad_config: Res<GamepadConfig>,
+    audio_settings: Res<AudioSettings>,
+) {
+    save_data.settings.window = window_state.clone();
+    save_data.settings.controls = input_map.clone();
+    save_data.settings.gamepad = gamepad_config.clone();
+    save_data.settings.audio = audio_settings.clone();
+    save(SAVE_DATA_KEY, &*save_data);
+}
+
+fn save_on_exit(
+    save_data: ResMut<SaveData>,
+    window_state: Res<WindowState>,
+    input_map: Res<InputMap>,
+    gamepad_config: Res<GamepadConfig>,
+    audio_settings: Res<AudioSettings>,
+) {
+    info!("Saving game data");
+
+    save_settings(save_data, window_state, input_map, gamepad_config, audio_settings);
+}
+
+/// Load RON data from storage. Falls back to the default if it's missing or can't be read,
+/// adding a warning to `warnings`.
+pub fn load<T: DeserializeOwned + Default>(key: &str, warnings: &mut Vec<String>) -> T {
+    let contents = match storage::read(key) {
+        Ok(Some(contents)) => contents,
+        Ok(None) => return default(),
+        Err(e) => {
+            warnings.push(format!("Could not read {}, using defaults: {}", key, e));
+            return default();
+        }
+    };
+    match ron::from_str(&contents) {
+        Ok(value) => value,
+        Err(e) => {
+            // Move the bad data out of the way so it isn't lost when we next save.
+            warnings.push(format!("Could not parse {}, using defaults: {}", key, e));
+            storage::discard(key);
+            default()
+        }
+    }
+}
+
+/// Save data to storage as RON. Errors are logged and otherwise ignored.
+pub fn save<T: Serialize>(key: &str, value: &T) {
+    if let Err(e) = try_save(key, value) {
+        error!("Could not save {}: {}", key, e);
+    }
+}
+
+fn try_save<T: Serialize>(key: &str, value: &T) -> Result<(), String> {
+    let pretty_config = ron::ser::PrettyConfig::default();
+    ron::ser::to_string_pretty(value, pretty_config)
+        .map_err(|e| e.to_string())
+        .and_then(|contents| storage::write(key, &contents))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod storage {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use directories::ProjectDirs;
+
+    fn path(key: &str) -> Result<PathBuf, String> {
+        let dirs = ProjectDirs::from("", "", "cat-chaser")
+            .ok_or("Could not find a data directory")?;
+        Ok(dirs.data_dir().join(format!("{key}.ron")))
+    }
+
+    pub fn read(key: &str) -> Result<Option<String>, String> {
+        let path = path(key)?;
+        if !path.is_file() {
+            return Ok(None);
+        }
+        fs::read_to_string(&path)
+            .map(Some)
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn write(key: &str, contents: &str) -> Result<(), String> {
+        let path = path(key)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        fs::write(&path, contents).map_err(|e| e.to_string())
+    }
+
+    pub fn discard(key: &str) {
+        if let Ok(path) = path(key) {
+            let _ = fs::rename(&path, path.with_extension("ron.bak"));
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod storage {
+    use web_sys::Storage;
+
+    fn local_storage() -> Result<Storage, String> {
+        web_sys::window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .ok_or_else(|| "localStorage is not available".into())
+    }
+
+    fn storage_key(key: &str) -> String {
+        format!("cat-chaser.{key}")
+    }
+
+    pub fn read(key: &str) -> Result<Option<String>, String> {
+        local_storage()?
+            .get_item(&storage_key(key))
+            .map_err(|e| format!("{e:?}"))
+    }
+
+    pub fn write(key: &str, contents: &str) -> Result<(), String> {
+        local_storage()?
+            .set_item(&storage_key(key), contents)
+            .map_err(|e| format!("{e:?}"))
+    }
+
+    pub fn discard(key: &str) {
+        if let Ok(storage) = local_storage() {
+            let key = storage_key(key);
+            // Keep a copy, like the `.ron.bak` file on native.
+            if let Ok(Some(contents)) = storage.get_item(&key) {
+                let _ = storage.set_item(&format!("{key}.bak"), &contents);
+            }
+            let _ = storage.remove_item(&key);
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::time::Stopwatch;
+
+use crate::{
+    game::{CatStats, GameSet, GameState},
+    level::{CurrentLevel, RespawnLevel},
+};
+
+const POINTS_PER_CAT: u32 = 100;
+const POINTS_PER_SECOND_UNDER_PAR: f32 = 20.0;
+const HIT_PENALTY: u32 = 50;
+const BARK_PENALTY: u32 = 5;
+/// Getting hit more than this many times costs a star.
+const MAX_HITS_FOR_FULL_STARS: u32 = 2;
+
+pub struct ScorePlugin;
+
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<LevelStats>()
+            .init_resource::<LevelResult>()
+            .add_systems(OnEnter(GameState::Playing), reset_level_stats)
+            // A respawned level is a fresh attempt.
+            .add_systems(Update, reset_level_stats.run_if(on_message::<RespawnLevel>))
+            .add_systems(OnEnter(GameState::LevelClear), compute_level_result)
+            .add_systems(OnEnter(GameState::Victory), compute_level_result)
+            .add_systems(FixedUpdate, tick_level_clock.in_set(GameSet::Logic));
+    }
+}
+
+/// Tracks how the current level is going.
+#[derive(Default, Resource)]
+pub struct LevelStats {
+    pub clock: Stopwatch,
+    pub times_hit: u32,
+    pub barks: u32,
+}
+
+/// The outcome of the last cleared level.
+#[derive(Clone, Debug, Default, PartialEq, Resource)]
+pub struct LevelResult {
+    pub level_index: usize,
+    pub time: Duration,
+    pub score: u32,
+    pub stars: u8,
+}
+
+fn reset_level_stats(
+    mut stats: ResMut<LevelStats>,
+) {
+    *stats = LevelStats::default();
+}
+
+fn tick_level_clock(
+    time: Res<Time>,
+    mut stats: ResMut<LevelStats>,
+) {
+    stats.clock.tick(time.delta());
+}
+
+pub fn compute_level_result(
+    stats: Res<LevelStats>,
+    cat_stats: Res<CatStats>,
+    current_level: Res<CurrentLevel>,
+    mut result: ResMut<LevelResult>,
+) {
+    let time = stats.clock.elapsed();
+    let par = current_level.data.par_time();
+    *result = LevelResult {
+        level_index: current_level.index,
+        time,
+        score: score(time.as_secs_f32(), par, cat_stats.in_pen(), stats.times_hit, stats.barks),
+        stars: stars(time.as_secs_f32(), par, stats.times_hit),
+    };
+    debug!("Level result: {:?}", *result);
+}
+
+fn score(time: f32, par: f32, cats: u32, times_hit: u32, barks: u32) -> u32 {
+    let time_bonus = ((par - time).max(0.0) * POINTS_PER_SECOND_UNDER_PAR) as u32;
+    (cats * POINTS_PER_CAT + time_bonus)
+        .saturating_sub(times_hit * HIT_PENALTY)
+        .saturating_sub(barks * BARK_PENALTY)
+}
+
+fn stars(time: f32, par: f32, times_hit: u32) -> u8 {
+    let stars: u8 = if time <= par {
+        3
+    } else if time <= par * 1.5 {
+        2
+    } else {
+        1
+    };
+    if times_hit > MAX_HITS_FOR_FULL_STARS {
+        stars.saturating_sub(1).max(1)
+    } else {
+        stars
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn score_adds_time_bonus_and_subtracts_penalties() {
+        // 3 cats, 10s under par, hit once, barked twice.
+        assert_eq!(score(20.0, 30.0, 3, 1, 2), 300 + 200 - 50 - 10);
+    }
+
+    #[test]
+    fn score_has_no_time_bonus_over_par() {
+        assert_eq!(score(45.0, 30.0, 2, 0, 0), 200);
+    }
+
+    #[test]
+    fn score_does_not_go_below_zero() {
+        assert_eq!(score(100.0, 30.0, 1, 5, 50), 0);
+    }
+
+    #[test]
+    fn stars_depend_on_time() {
+        assert_eq!(stars(30.0, 30.0, 0), 3);
+        assert_eq!(stars(45.0, 30.0, 0), 2);
+        assert_eq!(stars(45.1, 30.0, 0), 1);
+    }
+
+    #[test]
+    fn getting_hit_costs_a_star_but_keeps_at_least_one() {
+        assert_eq!(stars(20.0, 30.0, MAX_HITS_FOR_FULL_STARS), 3);
+        assert_eq!(stars(20.0, 30.0, MAX_HITS_FOR_FULL_STARS + 1), 2);
+        assert_eq!(stars(100.0, 30.0, MAX_HITS_FOR_FULL_STARS + 1), 1);
+    }
+}
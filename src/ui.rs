@@ -1,21 +1,38 @@
 use bevy::prelude::*;
 
 // mod classes;
+mod buttons;
+mod controls;
+mod demo;
 mod hud;
+mod loading;
 mod menus;
+mod options;
 mod party;
+mod pause;
 
 use crate::window::{WindowState, update_window_state};
 
+/// Color for whatever is selected or important in menus.
+const HIGHLIGHT_COLOR: Color = Color::srgb(1.0, 0.85, 0.3);
+/// Color for things that can't be picked yet.
+const DISABLED_COLOR: Color = Color::srgb(0.5, 0.5, 0.5);
+
 pub struct UiPlugin;
 
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
         app
             .add_plugins((
+                buttons::ButtonsPlugin,
+                controls::ControlsPlugin,
+                demo::DemoPlugin,
                 hud::HudPlugin,
+                loading::LoadingPlugin,
                 menus::MenusPlugin,
+                options::OptionsPlugin,
                 party::PartyPlugin,
+                pause::PausePlugin,
             ))
             .add_systems(PostUpdate, update_ui_scale.after(update_window_state));
     }
@@ -0,0 +1,228 @@
+use bevy::prelude::*;
+use bevy_egui::input::egui_wants_any_input;
+
+use crate::{
+    AppState,
+    input::{Action, ActionState, InputDevice},
+};
+
+use super::{DISABLED_COLOR, HIGHLIGHT_COLOR};
+
+pub struct ButtonsPlugin;
+
+impl Plugin for ButtonsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_message::<ButtonPressed>()
+            .add_observer(focus_on_hover)
+            .add_observer(press_on_click)
+            .add_systems(Update, (
+                navigate_menu_buttons.run_if(not(egui_wants_any_input).and(not(any_with_component::<MenuBlocked>))),
+                go_to_target,
+                highlight_focused_button,
+            ).chain());
+    }
+}
+
+/// A button on a menu screen that can be focused and pressed.
+/// Buttons on a screen should share a parent so they can be navigated in order.
+#[derive(Component)]
+pub struct MenuButton;
+
+/// Goes to another screen when its button is pressed.
+#[derive(Component)]
+pub struct MenuTarget(pub AppState);
+
+/// The button that Confirm will press.
+#[derive(Component)]
+pub struct Focused;
+
+/// A button that can be focused but not pressed, like a locked level.
+#[derive(Component)]
+pub struct Disabled;
+
+/// Stops menu buttons from being navigated or pressed while it exists, e.g. while waiting for a key to rebind.
+#[derive(Component)]
+pub struct MenuBlocked;
+
+/// Sent when a menu button is pressed with Confirm or clicked.
+#[derive(Message)]
+pub struct ButtonPressed {
+    pub entity: Entity,
+    /// The device that pressed Confirm, or `None` if the button was clicked.
+    pub device: Option<InputDevice>,
+}
+
+/// A button that goes to another screen.
+pub fn menu_button(label: &str, target: AppState, font: Handle<Font>) -> impl Bundle {
+    (
+        Name::new(format!("{}Button", label)),
+        MenuTarget(target),
+        button(label, TextFont {
+            font,
+            font_size: 13.0,
+            ..default()
+        }),
+    )
+}
+
+/// A button that menus react to by reading `ButtonPressed`. Its text can be changed with `set_button_text`.
+pub fn button(label: impl Into<String>, text_font: TextFont) -> impl Bundle {
+    (
+        MenuButton,
+        Node {
+            padding: UiRect::axes(Val::Px(6.0), Val::Px(1.0)),
+            border: UiRect::all(Val::Px(1.0)),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        BorderColor::all(Color::NONE),
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+        children![(
+            Text(label.into()),
+            TextColor(Color::WHITE),
+            text_font,
+        )],
+    )
+}
+
+/// Change the text of a button, if it's different.
+pub fn set_button_text(children: &Children, text_q: &mut Query<&mut Text>, text: String) {
+    for child in children.iter() {
+        if let Ok(mut button_text) = text_q.get_mut(child) && button_text.0 != text {
+            button_text.0 = text;
+            return;
+        }
+    }
+}
+
+pub fn navigate_menu_buttons(
+    mut commands: Commands,
+    actions: Res<ActionState>,
+    button_q: Query<(Entity, &ChildOf, Has<Focused>, Has<Disabled>), With<MenuButton>>,
+    children_q: Query<&Children>,
+    node_q: Query<&Node>,
+    mut pressed: MessageWriter<ButtonPressed>,
+) {
+    let Some((_, child_of, ..)) = button_q.iter().find(|(_, _, focused, _)| *focused)
+        .or_else(|| button_q.iter().next())
+    else {
+        return;
+    };
+
+    // Navigate between the buttons in the order they were spawned.
+    let parent = child_of.parent();
+    let Ok(siblings) = children_q.get(parent) else {
+        return;
+    };
+    let buttons: Vec<Entity> = siblings.iter()
+        .filter(|entity| button_q.contains(*entity))
+        .collect();
+    let focused = buttons.iter().position(|entity| button_q.get(*entity).is_ok_and(|(_, _, focused, _)| focused));
+    let Some(focused) = focused else {
+        // Focus the first button when a screen opens.
+        if let Some(first) = buttons.first() {
+            commands.entity(*first).insert(Focused);
+        }
+        return;
+    };
+
+    // Move along the direction the buttons are laid out in, which leaves the other one for adjusting settings.
+    let vertical = node_q.get(parent)
+        .is_ok_and(|node| matches!(node.flex_direction, FlexDirection::Column | FlexDirection::ColumnReverse));
+    let (prev_action, next_action) = if vertical {
+        (Action::MoveUp, Action::MoveDown)
+    } else {
+        (Action::MoveLeft, Action::MoveRight)
+    };
+    let new_focus = if actions.just_pressed(prev_action) {
+        (focused + buttons.len() - 1) % buttons.len()
+    } else if actions.just_pressed(next_action) {
+        (focused + 1) % buttons.len()
+    } else {
+        focused
+    };
+    if new_focus != focused {
+        commands.entity(buttons[focused]).remove::<Focused>();
+        commands.entity(buttons[new_focus]).insert(Focused);
+    }
+
+    let confirmed_by = actions.input_devices().find(|device| actions.device_just_pressed(*device, Action::Confirm));
+    let disabled = button_q.get(buttons[focused]).is_ok_and(|(.., disabled)| disabled);
+    if let Some(device) = confirmed_by && !disabled {
+        pressed.write(ButtonPressed {
+            entity: buttons[focused],
+            device: Some(device),
+        });
+    }
+}
+
+fn go_to_target(
+    mut presses: MessageReader<ButtonPressed>,
+    target_q: Query<&MenuTarget>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    for pressed in presses.read() {
+        if let Ok(target) = target_q.get(pressed.entity) {
+            next_state.set(target.0);
+        }
+    }
+}
+
+fn focus_on_hover(
+    over: On<Pointer<Over>>,
+    mut commands: Commands,
+    button_q: Query<(), With<MenuButton>>,
+    focused_q: Query<Entity, With<Focused>>,
+    blocked_q: Query<(), With<MenuBlocked>>,
+) {
+    // Pointer events bubble up from the button's text, so only react once they reach the button.
+    if !button_q.contains(over.entity) || !blocked_q.is_empty() {
+        return;
+    }
+    for entity in focused_q.iter() {
+        commands.entity(entity).remove::<Focused>();
+    }
+    commands.entity(over.entity).insert(Focused);
+}
+
+fn press_on_click(
+    click: On<Pointer<Click>>,
+    button_q: Query<(), (With<MenuButton>, Without<Disabled>)>,
+    blocked_q: Query<(), With<MenuBlocked>>,
+    mut pressed: MessageWriter<ButtonPressed>,
+) {
+    if click.event().event.button != PointerButton::Primary || !blocked_q.is_empty() {
+        return;
+    }
+    if button_q.contains(click.entity) {
+        pressed.write(ButtonPressed {
+            entity: click.entity,
+            device: None,
+        });
+    }
+}
+
+fn highlight_focused_button(
+    mut button_q: Query<(Has<Focused>, Has<Disabled>, &mut BorderColor, &Children), With<MenuButton>>,
+    mut text_q: Query<&mut TextColor>,
+) {
+    for (focused, disabled, mut border, children) in button_q.iter_mut() {
+        let color = if focused {
+            HIGHLIGHT_COLOR
+        } else if disabled {
+            DISABLED_COLOR
+        } else {
+            Color::WHITE
+        };
+        let border_color = BorderColor::all(if focused { HIGHLIGHT_COLOR } else { Color::NONE });
+        if *border != border_color {
+            *border = border_color;
+        }
+        for child in children.iter() {
+            if let Ok(mut text_color) = text_q.get_mut(child) && text_color.0 != color {
+                text_color.0 = color;
+            }
+        }
+    }
+}
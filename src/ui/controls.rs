@@ -0,0 +1,275 @@
+use bevy::prelude::*;
+use bevy_egui::input::egui_wants_any_input;
+
+use crate::{
+    AppState,
+    assets::GameAssets,
+    input::{Action, ActionState, Binding, GamepadConfig, InputMap},
+};
+
+use super::buttons::{button, navigate_menu_buttons, set_button_text, ButtonPressed, Focused, MenuBlocked, MenuTarget};
+
+/// Rows are every action, then the gamepad options, then a reset button.
+const GAMEPAD_ROW: usize = Action::ALL.len();
+const RESET_ROW: usize = GAMEPAD_ROW + GamepadOption::ALL.len();
+
+/// Gamepad settings that can be adjusted with left and right.
+#[derive(Clone, Copy)]
+enum GamepadOption {
+    InnerDeadzone,
+    OuterDeadzone,
+    ResponseCurve,
+    MoveStick,
+    BarkWithStick,
+    BarkWithTriggers,
+}
+
+impl GamepadOption {
+    const ALL: [GamepadOption; 6] = [
+        GamepadOption::InnerDeadzone,
+        GamepadOption::OuterDeadzone,
+        GamepadOption::ResponseCurve,
+        GamepadOption::MoveStick,
+        GamepadOption::BarkWithStick,
+        GamepadOption::BarkWithTriggers,
+    ];
+
+    /// The option shown on the given row, if it's one of the gamepad rows.
+    fn from_row(row: usize) -> Option<GamepadOption> {
+        row.checked_sub(GAMEPAD_ROW).and_then(|i| GamepadOption::ALL.get(i)).copied()
+    }
+
+    fn text(&self, config: &GamepadConfig) -> String {
+        let on_off = |on: bool| if on { "On" } else { "Off" };
+        match self {
+            GamepadOption::InnerDeadzone => format!("Deadzone: {:.2}", config.inner_deadzone),
+            GamepadOption::OuterDeadzone => format!("Outer Deadzone: {:.2}", config.outer_deadzone),
+            GamepadOption::ResponseCurve => format!("Response Curve: {:.1}", config.response_curve),
+            GamepadOption::MoveStick => format!("Move Stick: {:?}", config.move_stick),
+            GamepadOption::BarkWithStick => format!("Bark with Other Stick: {}", on_off(config.bark_with_stick)),
+            GamepadOption::BarkWithTriggers => format!("Bark with Triggers: {}", on_off(config.bark_with_triggers)),
+        }
+    }
+
+    /// Step the option up or down. Toggles ignore the direction.
+    fn adjust(&self, config: &mut GamepadConfig, dir: f32) {
+        match self {
+            GamepadOption::InnerDeadzone => {
+                config.inner_deadzone = (config.inner_deadzone + dir * 0.05).clamp(0.0, config.outer_deadzone - 0.05);
+            }
+            GamepadOption::OuterDeadzone => {
+                config.outer_deadzone = (config.outer_deadzone + dir * 0.05).clamp(config.inner_deadzone + 0.05, 1.0);
+            }
+            GamepadOption::ResponseCurve => {
+                config.response_curve = (config.response_curve + dir * 0.1).clamp(0.5, 3.0);
+            }
+            GamepadOption::MoveStick => config.move_stick = config.move_stick.other(),
+            GamepadOption::BarkWithStick => config.bark_with_stick = !config.bark_with_stick,
+            GamepadOption::BarkWithTriggers => config.bark_with_triggers = !config.bark_with_triggers,
+        }
+    }
+}
+
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnEnter(AppState::Controls), show_controls)
+            .add_systems(OnExit(AppState::Controls), clear_controls)
+            .add_systems(Update, (
+                // After navigating, so the key that finishes rebinding can't also press a button.
+                handle_controls_input.run_if(not(egui_wants_any_input)).after(navigate_menu_buttons),
+                update_controls_menu,
+            ).chain().run_if(in_state(AppState::Controls)));
+    }
+}
+
+#[derive(Component)]
+struct ControlsMenu {
+    /// Waiting for a key or button to bind to this action.
+    listening: Option<Action>,
+}
+
+#[derive(Component)]
+struct ControlsEntry(usize);
+
+fn show_controls(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+) {
+    let text_font = TextFont {
+        font: assets.font.clone(),
+        font_size: 11.0,
+        ..default()
+    };
+    commands.spawn((
+        Name::new("ControlsMenu"),
+        ControlsMenu {
+            listening: None,
+        },
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            padding: UiRect::all(Val::Px(8.0)),
+            row_gap: Val::Px(2.0),
+            ..default()
+        },
+        BackgroundColor(Color::srgb(0.1, 0.1, 0.15)),
+    )).with_children(|parent| {
+        parent.spawn((
+            Text("Controls".into()),
+            TextColor(Color::WHITE),
+            TextFont {
+                font_size: 14.0,
+                ..text_font.clone()
+            },
+        ));
+
+        // Wrap into a second column since there are too many rows to fit in one.
+        parent.spawn(Node {
+            height: Val::Percent(85.0),
+            flex_direction: FlexDirection::Column,
+            flex_wrap: FlexWrap::Wrap,
+            align_items: AlignItems::FlexStart,
+            column_gap: Val::Px(12.0),
+            ..default()
+        }).with_children(|parent| {
+            for i in 0..=RESET_ROW {
+                parent.spawn((ControlsEntry(i), button("", text_font.clone())));
+            }
+            parent.spawn((MenuTarget(AppState::StartMenu), button("Back", text_font.clone())));
+        });
+
+        parent.spawn((
+            Text("Confirm to rebind, Left/Right to adjust, Back to return".into()),
+            TextColor(Color::WHITE),
+            text_font.clone(),
+            Node {
+                position_type: PositionType::Absolute,
+                right: Val::Px(4.0),
+                bottom: Val::Px(6.0),
+                ..default()
+            },
+        ));
+    });
+}
+
+fn handle_controls_input(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    actions: Res<ActionState>,
+    mut presses: MessageReader<ButtonPressed>,
+    mut input_map: ResMut<InputMap>,
+    mut gamepad_config: ResMut<GamepadConfig>,
+    mut menu_q: Query<(Entity, &mut ControlsMenu)>,
+    entry_q: Query<(&ControlsEntry, Has<Focused>)>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let Ok((menu_entity, mut menu)) = menu_q.single_mut() else {
+        return;
+    };
+
+    if let Some(action) = menu.listening {
+        // Bind whatever is pressed next, unless it's Back. Keys replace keys and buttons replace buttons.
+        if actions.just_pressed(Action::Back) {
+            debug!("Cancelled binding {:?}", action);
+        } else if let Some(key) = keys.get_just_pressed().next() {
+            info!("Bound {:?} to {:?}", key, action);
+            input_map.bind_key(action, *key);
+        } else if let Some(button) = gamepads.iter().find_map(|gamepad| gamepad.get_just_pressed().next()) {
+            info!("Bound {:?} to {:?}", button, action);
+            input_map.bind_button(action, *button);
+        } else {
+            return;
+        }
+        menu.listening = None;
+        commands.entity(menu_entity).remove::<MenuBlocked>();
+        return;
+    }
+
+    for pressed in presses.read() {
+        let Ok((entry, _)) = entry_q.get(pressed.entity) else {
+            continue;
+        };
+        if let Some(action) = Action::ALL.get(entry.0) {
+            // Stop the buttons from reacting to the key being bound.
+            menu.listening = Some(*action);
+            commands.entity(menu_entity).insert(MenuBlocked);
+        } else if let Some(option) = GamepadOption::from_row(entry.0) {
+            option.adjust(&mut gamepad_config, 1.0);
+        } else if entry.0 == RESET_ROW {
+            *input_map = InputMap::default();
+            *gamepad_config = GamepadConfig::default();
+        }
+    }
+
+    // Left and right adjust the focused gamepad option.
+    let focused_option = entry_q.iter()
+        .find(|(_, focused)| *focused)
+        .and_then(|(entry, _)| GamepadOption::from_row(entry.0));
+    if let Some(option) = focused_option {
+        if actions.just_pressed(Action::MoveLeft) {
+            option.adjust(&mut gamepad_config, -1.0);
+        } else if actions.just_pressed(Action::MoveRight) {
+            option.adjust(&mut gamepad_config, 1.0);
+        }
+    }
+
+    if actions.just_pressed(Action::Back) {
+        next_state.set(AppState::StartMenu);
+    }
+}
+
+fn update_controls_menu(
+    menu_q: Query<Ref<ControlsMenu>>,
+    input_map: Res<InputMap>,
+    gamepad_config: Res<GamepadConfig>,
+    entry_q: Query<(&ControlsEntry, &Children)>,
+    mut text_q: Query<&mut Text>,
+) {
+    let Ok(menu) = menu_q.single() else {
+        return;
+    };
+    if !menu.is_changed() && !input_map.is_changed() && !gamepad_config.is_changed() {
+        return;
+    }
+
+    for (entry, children) in entry_q.iter() {
+        let text = match Action::ALL.get(entry.0) {
+            Some(action) if menu.listening == Some(*action) => {
+                format!("{}: Press a key or button, or Back to cancel", action.name())
+            }
+            Some(action) => {
+                format!("{}: {}", action.name(), binding_text(&input_map.binding(*action)))
+            }
+            None => match GamepadOption::from_row(entry.0) {
+                Some(option) => option.text(&gamepad_config),
+                None => "Reset to Defaults".into(),
+            },
+        };
+        set_button_text(children, &mut text_q, text);
+    }
+}
+
+fn binding_text(binding: &Binding) -> String {
+    let keys = binding.keys.iter().map(|key| format!("{:?}", key));
+    let buttons = binding.buttons.iter().map(|button| format!("{:?}", button));
+    let names: Vec<String> = keys.chain(buttons).collect();
+    if names.is_empty() {
+        "Unbound".into()
+    } else {
+        names.join(", ")
+    }
+}
+
+fn clear_controls(
+    mut commands: Commands,
+    menu_q: Query<Entity, With<ControlsMenu>>,
+) {
+    for entity in menu_q.iter() {
+        commands.entity(entity).despawn();
+    }
+}
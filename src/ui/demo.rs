@@ -0,0 +1,148 @@
+use bevy::prelude::*;
+
+use crate::{
+    AppState,
+    assets::GameAssets,
+    autopilot::Demo,
+    game::GameState,
+    input::{Action, ActionState, Players},
+    level::{CurrentLevel, Levels},
+    rng::GameRng,
+};
+
+/// Seconds on the title screen without any input before a demo starts.
+const IDLE_TIME: f32 = 20.0;
+/// Longest a demo plays before going back to the title screen.
+const DEMO_TIME: f32 = 60.0;
+/// How long to show off a cleared level before going back.
+const DEMO_CLEAR_TIME: f32 = 3.0;
+
+pub struct DemoPlugin;
+
+impl Plugin for DemoPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<IdleTimer>()
+            .add_systems(OnEnter(AppState::StartMenu), reset_idle_timer)
+            .add_systems(OnEnter(GameState::LevelClear), finish_demo_soon.run_if(resource_exists::<Demo>))
+            .add_systems(OnEnter(GameState::Victory), finish_demo_soon.run_if(resource_exists::<Demo>))
+            .add_systems(OnExit(AppState::Playing), end_demo.run_if(resource_exists::<Demo>))
+            .add_systems(Update, (
+                start_demo_when_idle.run_if(in_state(AppState::StartMenu)),
+                run_demo.run_if(in_state(AppState::Playing).and(resource_exists::<Demo>)),
+            ));
+    }
+}
+
+#[derive(Resource)]
+struct IdleTimer(Timer);
+
+impl Default for IdleTimer {
+    fn default() -> Self {
+        Self(Timer::from_seconds(IDLE_TIME, TimerMode::Once))
+    }
+}
+
+#[derive(Resource)]
+struct DemoTimer(Timer);
+
+#[derive(Component)]
+struct DemoLabel;
+
+/// Whether any key, mouse button or gamepad action was just pressed.
+fn any_just_pressed(keys: &ButtonInput<KeyCode>, mouse: &ButtonInput<MouseButton>, actions: &ActionState) -> bool {
+    keys.get_just_pressed().next().is_some()
+        || mouse.get_just_pressed().next().is_some()
+        || Action::ALL.into_iter().any(|action| actions.just_pressed(action))
+}
+
+fn reset_idle_timer(
+    mut idle_timer: ResMut<IdleTimer>,
+) {
+    idle_timer.0.reset();
+}
+
+fn start_demo_when_idle(
+    mut commands: Commands,
+    time: Res<Time>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    actions: Res<ActionState>,
+    assets: Res<GameAssets>,
+    levels: Res<Levels>,
+    mut idle_timer: ResMut<IdleTimer>,
+    mut players: ResMut<Players>,
+    mut rng: ResMut<GameRng>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if any_just_pressed(&keys, &mouse, &actions) {
+        idle_timer.0.reset();
+        return;
+    }
+    if !idle_timer.0.tick(time.delta()).just_finished() || levels.len() < 2 {
+        return;
+    }
+
+    // Show off a random level, skipping the test level.
+    let level_index = rng.level.usize(1..levels.len());
+    debug!("Starting demo of level {}", level_index);
+    current_level.start_index = Some(level_index);
+    commands.insert_resource(Demo {
+        saved_players: std::mem::take(&mut players.devices),
+    });
+    commands.insert_resource(DemoTimer(Timer::from_seconds(DEMO_TIME, TimerMode::Once)));
+    commands.spawn((
+        Name::new("DemoLabel"),
+        DemoLabel,
+        Text("Demo - Press any button".into()),
+        TextColor(Color::WHITE),
+        TextFont {
+            font: assets.font.clone(),
+            font_size: 11.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            right: Val::Px(4.0),
+            bottom: Val::Px(6.0),
+            ..default()
+        },
+        GlobalZIndex(10),
+    ));
+    next_state.set(AppState::Playing);
+}
+
+fn run_demo(
+    time: Res<Time>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    actions: Res<ActionState>,
+    mut demo_timer: ResMut<DemoTimer>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if any_just_pressed(&keys, &mouse, &actions) || demo_timer.0.tick(time.delta()).is_finished() {
+        next_state.set(AppState::StartMenu);
+    }
+}
+
+fn finish_demo_soon(
+    mut demo_timer: ResMut<DemoTimer>,
+) {
+    demo_timer.0 = Timer::from_seconds(DEMO_CLEAR_TIME, TimerMode::Once);
+}
+
+fn end_demo(
+    mut commands: Commands,
+    demo: Res<Demo>,
+    mut players: ResMut<Players>,
+    label_q: Query<Entity, With<DemoLabel>>,
+) {
+    debug!("Ending demo");
+    players.devices = demo.saved_players.clone();
+    commands.remove_resource::<Demo>();
+    commands.remove_resource::<DemoTimer>();
+    for entity in label_q.iter() {
+        commands.entity(entity).despawn();
+    }
+}
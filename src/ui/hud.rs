@@ -3,7 +3,11 @@ use bevy::prelude::*;
 use crate::{
     AppState,
     assets::GameAssets,
-    game::{self, CatStats, GameState},
+    dog::{self, Dog},
+    game::{CatStats, GameSet, GameState},
+    input::{PlayerInput, Players},
+    level::CurrentLevel,
+    score::{LevelResult, LevelStats},
 };
 
 pub struct HudPlugin;
@@ -14,9 +18,12 @@ impl Plugin for HudPlugin {
             .add_systems(OnEnter(AppState::Playing), setup_hud)
             .add_systems(OnExit(AppState::Playing), destroy_hud)
             .add_systems(Update, (
-                update_cat_tracker.after(game::update_cat_stats),
+                update_cat_tracker,
+                update_bark_meter,
+                update_level_clock,
                 update_next_level_prompt,
-            ).run_if(in_state(AppState::Playing)));
+                update_level_results,
+            ).in_set(GameSet::Presentation));
     }
 }
 
@@ -26,6 +33,22 @@ struct HudRoot;
 #[derive(Component)]
 struct CatTracker;
 
+/// Bark charge for the given player's dog.
+#[derive(Component)]
+struct BarkMeter(usize);
+
+#[derive(Component)]
+struct LevelClock;
+
+#[derive(Component)]
+struct LevelResultsPanel;
+
+#[derive(Component)]
+struct LevelResultsText;
+
+#[derive(Component)]
+struct LevelResultStar(u8);
+
 #[derive(Component)]
 struct NextLevelText;
 
@@ -35,6 +58,7 @@ struct VictoryText;
 fn setup_hud(
     mut commands: Commands,
     assets: Res<GameAssets>,
+    players: Res<Players>,
 ) {
     // Set up Cat Tracker.
     let cat_tracker = (
@@ -79,6 +103,138 @@ fn setup_hud(
         ],
     );
 
+    // Set up Bark meters, one for each player.
+    let num_players = players.count();
+    let font = assets.font.clone();
+    let bark_meter = move |player: usize| (
+        Name::new("BarkMeter"),
+        Node {
+            align_items: AlignItems::Center,
+            column_gap: Val::Px(4.0),
+            ..default()
+        },
+        children![
+            (
+                Text(if num_players > 1 { format!("P{}", player + 1) } else { "Bark".into() }),
+                TextColor(Color::WHITE),
+                TextFont {
+                    font: font.clone(),
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextShadow {
+                    offset: Vec2::splat(0.8),
+                    color: Color::BLACK,
+                },
+            ),
+            (
+                Node {
+                    width: Val::Px(30.0),
+                    height: Val::Px(4.0),
+                    ..default()
+                },
+                BackgroundColor(Color::BLACK),
+                children![(
+                    BarkMeter(player),
+                    Node {
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    BackgroundColor(dog::player_color(player)),
+                )],
+            ),
+        ],
+    );
+    let bark_meters = (
+        Name::new("BarkMeters"),
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(4.0),
+            top: Val::Px(2.0),
+            flex_direction: FlexDirection::Column,
+            ..default()
+        },
+        Children::spawn(SpawnIter((0..num_players).map(bark_meter))),
+    );
+
+    // Set up Level Clock.
+    let level_clock = (
+        Name::new("LevelClock"),
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            top: Val::Px(2.0),
+            ..default()
+        },
+        children![(
+            LevelClock,
+            Text("0.0".into()),
+            TextColor(Color::WHITE),
+            TextFont {
+                font: assets.font.clone(),
+                font_size: 14.0,
+                ..default()
+            },
+            TextShadow {
+                offset: Vec2::splat(0.8),
+                color: Color::BLACK,
+            },
+        )],
+    );
+
+    // Set up Level Results, shown after clearing a level.
+    let result_star = |i: u8| (
+        LevelResultStar(i),
+        ImageNode {
+            image: assets.cat_face.clone(),
+            ..default()
+        },
+    );
+    let level_results = (
+        LevelResultsPanel,
+        Name::new("LevelResults"),
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            top: Val::Percent(30.0),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            row_gap: Val::Px(4.0),
+            ..default()
+        },
+        Visibility::Hidden,
+        children![
+            (
+                LevelResultsText,
+                Text::default(),
+                TextColor(Color::WHITE),
+                TextFont {
+                    font: assets.font.clone(),
+                    font_size: 12.0,
+                    ..default()
+                },
+                TextShadow {
+                    offset: Vec2::splat(0.8),
+                    color: Color::BLACK,
+                },
+                TextLayout::new_with_justify(Justify::Center),
+            ),
+            (
+                Node {
+                    column_gap: Val::Px(4.0),
+                    ..default()
+                },
+                children![
+                    result_star(1),
+                    result_star(2),
+                    result_star(3),
+                ],
+            ),
+        ],
+    );
+
     // Set up Next Level text.
     let next_level_text = (
         NextLevelText,
@@ -141,6 +297,9 @@ fn setup_hud(
         },
         children![
             cat_tracker,
+            bark_meters,
+            level_clock,
+            level_results,
             next_level_text,
             victory_text,
         ],
@@ -169,6 +328,71 @@ fn update_cat_tracker(
     }
 }
 
+fn update_bark_meter(
+    mut meter_q: Query<(&BarkMeter, &mut Node)>,
+    dog_q: Query<(&Dog, &PlayerInput)>,
+) {
+    for (meter, mut node) in meter_q.iter_mut() {
+        let charge = dog_q.iter()
+            .find(|(_, input)| input.player == meter.0)
+            .map(|(dog, _)| dog.bark_charge())
+            .unwrap_or(1.0);
+        let width = Val::Percent(100.0 * charge);
+        if node.width != width {
+            node.width = width;
+        }
+    }
+}
+
+fn update_level_clock(
+    mut clock_q: Query<&mut Text, With<LevelClock>>,
+    stats: Res<LevelStats>,
+) {
+    if !stats.is_changed() {
+        return;
+    }
+
+    for mut clock_text in clock_q.iter_mut() {
+        clock_text.0 = format!("{:.1}", stats.clock.elapsed_secs());
+    }
+}
+
+fn update_level_results(
+    game_state: Res<State<GameState>>,
+    result: Res<LevelResult>,
+    current_level: Res<CurrentLevel>,
+    mut panel_q: Query<&mut Visibility, With<LevelResultsPanel>>,
+    mut text_q: Query<&mut Text, With<LevelResultsText>>,
+    mut star_q: Query<(&LevelResultStar, &mut ImageNode)>,
+) {
+    if game_state.is_changed() {
+        let show = matches!(game_state.get(), GameState::LevelClear | GameState::Victory);
+        for mut vis in panel_q.iter_mut() {
+            *vis = if show { Visibility::Inherited } else { Visibility::Hidden };
+        }
+    }
+
+    if !result.is_changed() {
+        return;
+    }
+    for mut text in text_q.iter_mut() {
+        text.0 = format!(
+            "Time: {:.1}s (Par: {:.0}s)\nScore: {}",
+            result.time.as_secs_f32(),
+            current_level.data.par_time(),
+            result.score,
+        );
+    }
+    for (star, mut image) in star_q.iter_mut() {
+        // Gray out the stars that weren't earned.
+        image.color = if star.0 <= result.stars {
+            Color::WHITE
+        } else {
+            Color::srgba(0.2, 0.2, 0.2, 0.8)
+        };
+    }
+}
+
 fn update_next_level_prompt(
     game_state: Res<State<GameState>>,
     mut next_level_q: Query<&mut Visibility, (With<NextLevelText>, Without<VictoryText>)>,
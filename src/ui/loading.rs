@@ -0,0 +1,213 @@
+use bevy::prelude::*;
+use bevy::asset::UntypedAssetLoadFailedEvent;
+use bevy_aseprite_ultra::prelude::*;
+use bevy_asset_loader::prelude::*;
+use iyes_progress::ProgressTracker;
+
+use crate::AppState;
+
+use super::HIGHLIGHT_COLOR;
+
+const BAR_WIDTH: f32 = 120.0;
+
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<FailedAssets>()
+            .add_systems(OnEnter(AppState::Loading), show_loading)
+            .add_systems(OnExit(AppState::Loading), clear_loading)
+            .add_systems(OnEnter(AppState::LoadingError), show_loading_error)
+            .add_systems(Update, (
+                record_failed_assets,
+                update_loading_bar,
+                spawn_loading_dog,
+            ).run_if(in_state(AppState::Loading)).after(LoadingStateSet(AppState::Loading)));
+    }
+}
+
+/// Assets for the loading screens, loaded on their own so they show up before everything else is ready.
+#[derive(Resource)]
+struct LoadingAssets {
+    // These share paths with GameAssets, so they don't get loaded twice.
+    font: Handle<Font>,
+    wizard_dog: Handle<Aseprite>,
+}
+
+/// Paths of assets that couldn't be loaded.
+#[derive(Default, Resource)]
+struct FailedAssets(Vec<String>);
+
+#[derive(Component)]
+struct LoadingScreen;
+
+#[derive(Component)]
+struct LoadingBarFill;
+
+#[derive(Component)]
+struct LoadingDog;
+
+fn show_loading(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    let assets = LoadingAssets {
+        font: asset_server.load("fonts/Kenney Pixel.ttf"),
+        wizard_dog: asset_server.load("sprites/wizard_dog.aseprite"),
+    };
+    commands.spawn((
+        Name::new("LoadingScreen"),
+        LoadingScreen,
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::End,
+            align_items: AlignItems::Center,
+            padding: UiRect::bottom(Val::Px(40.0)),
+            row_gap: Val::Px(4.0),
+            ..default()
+        },
+    )).with_children(|parent| {
+        parent.spawn((
+            Text("Loading...".into()),
+            TextColor(Color::WHITE),
+            TextFont {
+                font: assets.font.clone(),
+                font_size: 13.0,
+                ..default()
+            },
+        ));
+        parent.spawn((
+            Node {
+                width: Val::Px(BAR_WIDTH),
+                height: Val::Px(6.0),
+                border: UiRect::all(Val::Px(1.0)),
+                ..default()
+            },
+            BorderColor::all(Color::WHITE),
+        )).with_children(|parent| {
+            parent.spawn((
+                LoadingBarFill,
+                Node {
+                    width: Val::Percent(0.0),
+                    height: Val::Percent(100.0),
+                    ..default()
+                },
+                BackgroundColor(HIGHLIGHT_COLOR),
+            ));
+        });
+    });
+    commands.insert_resource(assets);
+}
+
+fn record_failed_assets(
+    mut failed_messages: MessageReader<UntypedAssetLoadFailedEvent>,
+    mut failed_assets: ResMut<FailedAssets>,
+) {
+    for failed in failed_messages.read() {
+        error!("Failed to load {}: {}", failed.path, failed.error);
+        failed_assets.0.push(failed.path.to_string());
+    }
+}
+
+fn update_loading_bar(
+    tracker: Res<ProgressTracker<AppState>>,
+    mut fill_q: Query<&mut Node, With<LoadingBarFill>>,
+) {
+    let progress = tracker.get_global_progress();
+    let fraction = if progress.total == 0 {
+        0.0
+    } else {
+        progress.done as f32 / progress.total as f32
+    };
+    for mut node in fill_q.iter_mut() {
+        node.width = Val::Percent(100.0 * fraction);
+    }
+}
+
+/// Show the doggo running once its sprite has loaded.
+fn spawn_loading_dog(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    assets: Res<LoadingAssets>,
+    dog_q: Query<(), With<LoadingDog>>,
+) {
+    if !dog_q.is_empty() || !asset_server.is_loaded_with_dependencies(&assets.wizard_dog) {
+        return;
+    }
+    commands.spawn((
+        Name::new("LoadingDog"),
+        LoadingDog,
+        Transform::from_xyz(0.0, 40.0, 0.0)
+            .with_scale(Vec3::new(5.0, 5.0, 1.0)),
+        AseAnimation {
+            aseprite: assets.wizard_dog.clone(),
+            animation: Animation::default()
+                .with_tag("run_front"),
+            ..default()
+        },
+    ));
+}
+
+fn clear_loading(
+    mut commands: Commands,
+    screen_q: Query<Entity, Or<(With<LoadingScreen>, With<LoadingDog>)>>,
+) {
+    for entity in screen_q.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn show_loading_error(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    assets: Res<LoadingAssets>,
+    failed_assets: Res<FailedAssets>,
+) {
+    // The font may be what failed to load, so fall back to the built-in one.
+    let font = if asset_server.is_loaded(&assets.font) {
+        assets.font.clone()
+    } else {
+        default()
+    };
+    let text_font = TextFont {
+        font,
+        font_size: 11.0,
+        ..default()
+    };
+    commands.spawn((
+        Name::new("LoadingError"),
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            padding: UiRect::all(Val::Px(8.0)),
+            row_gap: Val::Px(2.0),
+            ..default()
+        },
+        BackgroundColor(Color::srgb(0.15, 0.05, 0.05)),
+    )).with_children(|parent| {
+        parent.spawn((
+            Text("Couldn't load the game!".into()),
+            TextColor(Color::WHITE),
+            TextFont {
+                font_size: 14.0,
+                ..text_font.clone()
+            },
+        ));
+        parent.spawn((
+            Text("These files are missing or broken:".into()),
+            TextColor(Color::WHITE),
+            text_font.clone(),
+        ));
+        for path in failed_assets.0.iter() {
+            parent.spawn((
+                Text(format!("  {}", path)),
+                TextColor(HIGHLIGHT_COLOR),
+                text_font.clone(),
+            ));
+        }
+    });
+}
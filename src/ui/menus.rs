@@ -1,12 +1,22 @@
 use bevy::prelude::*;
 use bevy_aseprite_ultra::prelude::*;
 use bevy_egui::input::egui_wants_any_input;
+use bevy_kira_audio::{AudioChannel, AudioControl};
 
 use crate::{
     SCREEN_SIZE, AppState,
-    assets::GameAssets,
+    assets::{GameAssets, SfxAssets},
+    audio::UiChannel,
     cats,
-    utils::Blink,
+    dog,
+    input::{Action, ActionState, InputDevice, Players, MAX_PLAYERS},
+    level::{CurrentLevel, LevelData, Levels},
+    save::SaveData,
+};
+
+use super::{
+    DISABLED_COLOR,
+    buttons::{button, menu_button, ButtonPressed, Disabled, Focused, MenuTarget},
 };
 
 pub struct MenusPlugin;
@@ -16,17 +26,41 @@ impl Plugin for MenusPlugin {
         app
             .add_systems(OnEnter(AppState::StartMenu), show_start)
             .add_systems(OnExit(AppState::StartMenu), clear_start)
+            .add_systems(OnEnter(AppState::LevelSelect), show_level_select)
+            .add_systems(OnExit(AppState::LevelSelect), clear_level_select)
             .add_systems(OnEnter(AppState::Credits), show_credits)
             .add_systems(OnExit(AppState::Credits), clear_credits)
             .add_systems(OnEnter(AppState::HowToPlay), show_how_to_play)
             .add_systems(OnExit(AppState::HowToPlay), clear_how_to_play)
-            .add_systems(Update, handle_menu_input.run_if(not(egui_wants_any_input)));
+            .add_systems(OnEnter(AppState::Join), show_join)
+            .add_systems(OnExit(AppState::Join), clear_join)
+            .add_systems(Update, (
+                handle_menu_input.run_if(not(egui_wants_any_input)),
+                handle_level_select_input.run_if(in_state(AppState::LevelSelect).and(not(egui_wants_any_input))),
+                (
+                    handle_join_input.run_if(not(egui_wants_any_input)),
+                    update_join_slots,
+                ).chain().run_if(in_state(AppState::Join)),
+            ).chain());
     }
 }
 
 #[derive(Component)]
 pub struct MenuRoot;
 
+#[derive(Component)]
+struct LevelSelectEntry(usize);
+
+#[derive(Clone, Copy, Component)]
+enum JoinButton {
+    Play,
+    Back,
+}
+
+/// Shows who joined as the given player on the join screen.
+#[derive(Component)]
+struct JoinSlot(usize);
+
 fn show_start(
     mut commands: Commands,
     assets: Res<GameAssets>,
@@ -35,25 +69,22 @@ fn show_start(
         image: assets.start_menu.clone(),
         ..default()
     };
-    let start_text = (
-        Blink::from_seconds(0.5, true),
-        Text("Press Enter to play!".into()),
-        TextColor(Color::WHITE),
-        TextFont {
-            font: assets.font.clone(),
-            font_size: 13.0,
-            ..default()
-        },
-        TextShadow {
-            offset: Vec2::splat(0.8),
-            color: Color::BLACK,
-        },
+    let start_buttons = (
         Node {
             position_type: PositionType::Absolute,
             right: Val::Px(4.0),
             bottom: Val::Px(6.0),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(2.0),
             ..default()
         },
+        children![
+            menu_button("Play", AppState::HowToPlay, assets.font.clone()),
+            menu_button("Levels", AppState::LevelSelect, assets.font.clone()),
+            menu_button("Controls", AppState::Controls, assets.font.clone()),
+            menu_button("Options", AppState::Options, assets.font.clone()),
+            menu_button("Credits", AppState::Credits, assets.font.clone()),
+        ],
     );
     commands.spawn((
         MenuRoot,
@@ -64,40 +95,290 @@ fn show_start(
         },
         children![
             start_image,
-            start_text,
+            start_buttons,
         ],
     ));
 }
 
 fn handle_menu_input(
-    keys: Res<ButtonInput<KeyCode>>,
+    actions: Res<ActionState>,
     app_state: Res<State<AppState>>,
     mut next_state: ResMut<NextState<AppState>>,
 ) {
     match app_state.get() {
         AppState::Loading => {}
-        AppState::StartMenu => {
-            if keys.just_pressed(KeyCode::Tab) {
-                next_state.set(AppState::Credits);
-            } else if keys.just_pressed(KeyCode::Enter) {
-                next_state.set(AppState::HowToPlay);
+        AppState::LoadingError => {}
+        AppState::StartMenu => {}
+        AppState::LevelSelect | AppState::Credits | AppState::HowToPlay => {
+            if actions.just_pressed(Action::Back) {
+                next_state.set(AppState::StartMenu);
             }
         }
-        AppState::Credits => {
-            if keys.any_just_pressed([KeyCode::Tab, KeyCode::Enter]) {
-                next_state.set(AppState::StartMenu);
+        AppState::Join => {}
+        AppState::Controls => {}
+        AppState::Options => {}
+        AppState::Playing => {}
+    }
+}
+
+fn clear_start(
+    mut commands: Commands,
+    root_q: Query<Entity, With<MenuRoot>>,
+) {
+    for entity in root_q.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn show_level_select(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    levels: Res<Levels>,
+    save_data: Res<SaveData>,
+) {
+    let text_font = TextFont {
+        font: assets.font.clone(),
+        font_size: 11.0,
+        ..default()
+    };
+    let last_level = levels.len().saturating_sub(1).max(1);
+    let selected = save_data.highest_unlocked_level.clamp(1, last_level);
+    commands.spawn((
+        MenuRoot,
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::FlexStart,
+            padding: UiRect::all(Val::Px(8.0)),
+            row_gap: Val::Px(2.0),
+            ..default()
+        },
+        BackgroundColor(Color::srgb(0.1, 0.1, 0.15)),
+    )).with_children(|parent| {
+        parent.spawn((
+            Text("Pick a Level".into()),
+            TextColor(Color::WHITE),
+            TextFont {
+                font_size: 14.0,
+                ..text_font.clone()
+            },
+        ));
+
+        // Level 0 is only for testing, so skip it.
+        for (i, level) in levels.iter().enumerate().skip(1) {
+            let mut entry = parent.spawn((
+                LevelSelectEntry(i),
+                button(level_select_entry_text(i, level, &save_data), text_font.clone()),
+            ));
+            if i > save_data.highest_unlocked_level {
+                entry.insert(Disabled);
+            }
+            if i == selected {
+                entry.insert(Focused);
+            }
+        }
+        parent.spawn((MenuTarget(AppState::StartMenu), button("Back", text_font.clone())));
+
+        parent.spawn((
+            Text("Confirm to play, Back to return".into()),
+            TextColor(Color::WHITE),
+            text_font.clone(),
+            Node {
+                position_type: PositionType::Absolute,
+                right: Val::Px(4.0),
+                bottom: Val::Px(6.0),
+                ..default()
+            },
+        ));
+    });
+}
+
+fn level_select_entry_text(index: usize, level: &LevelData, save_data: &SaveData) -> String {
+    let name = if level.name.is_empty() {
+        format!("Level {}", index)
+    } else {
+        level.name.clone()
+    };
+    let cats = level.cat_counts();
+    let result = if index > save_data.highest_unlocked_level {
+        "Locked".into()
+    } else if let Some(best) = save_data.best_results.get(&index) {
+        format!("Best: {:.1}s {}", best.time, "*".repeat(best.stars as usize))
+    } else {
+        "New!".into()
+    };
+    format!(
+        "{}. {} - {} basic, {} kitten, {} chonk - {}",
+        index, name, cats.basic, cats.kitten, cats.chonk, result,
+    )
+}
+
+fn handle_level_select_input(
+    mut presses: MessageReader<ButtonPressed>,
+    entry_q: Query<&LevelSelectEntry>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    // Locked levels are disabled, so they can't be pressed.
+    for pressed in presses.read() {
+        if let Ok(entry) = entry_q.get(pressed.entity) {
+            current_level.start_index = Some(entry.0);
+            next_state.set(AppState::Join);
+        }
+    }
+}
+
+fn clear_level_select(
+    mut commands: Commands,
+    root_q: Query<Entity, With<MenuRoot>>,
+) {
+    for entity in root_q.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn show_join(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    mut players: ResMut<Players>,
+) {
+    // Everyone joins again each time.
+    players.devices.clear();
+
+    let text_font = TextFont {
+        font: assets.font.clone(),
+        font_size: 13.0,
+        ..default()
+    };
+    commands.spawn((
+        MenuRoot,
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            padding: UiRect::all(Val::Px(8.0)),
+            row_gap: Val::Px(4.0),
+            ..default()
+        },
+        BackgroundColor(Color::srgb(0.1, 0.1, 0.15)),
+    )).with_children(|parent| {
+        parent.spawn((
+            Text("Who's Playing?".into()),
+            TextColor(Color::WHITE),
+            TextFont {
+                font_size: 14.0,
+                ..text_font.clone()
+            },
+        ));
+
+        for i in 0..MAX_PLAYERS {
+            parent.spawn((
+                JoinSlot(i),
+                Text::default(),
+                TextColor(Color::WHITE),
+                text_font.clone(),
+            ));
+        }
+
+        parent.spawn(Node {
+            column_gap: Val::Px(4.0),
+            ..default()
+        }).with_children(|parent| {
+            parent.spawn((JoinButton::Play, button("Play", text_font.clone())));
+            parent.spawn((JoinButton::Back, button("Back", text_font.clone())));
+        });
+
+        parent.spawn((
+            Text("Confirm to join, Confirm again to play, Back to leave".into()),
+            TextColor(Color::WHITE),
+            TextFont {
+                font_size: 11.0,
+                ..text_font.clone()
+            },
+            Node {
+                position_type: PositionType::Absolute,
+                right: Val::Px(4.0),
+                bottom: Val::Px(6.0),
+                ..default()
+            },
+        ));
+    });
+}
+
+fn handle_join_input(
+    actions: Res<ActionState>,
+    mut presses: MessageReader<ButtonPressed>,
+    join_button_q: Query<&JoinButton>,
+    ui_audio: Res<AudioChannel<UiChannel>>,
+    sfx: Res<SfxAssets>,
+    mut players: ResMut<Players>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let mut start = false;
+    let mut back = false;
+
+    for pressed in presses.read() {
+        match (join_button_q.get(pressed.entity), pressed.device) {
+            // Confirm on Play joins, and confirming again starts.
+            (Ok(JoinButton::Play), Some(device)) if !players.has_joined(device) => {
+                players.join(device);
+                if players.has_joined(device) {
+                    ui_audio.play(sfx.dog_woof.clone());
+                }
             }
+            // Clicking Play starts with whoever joined, or just the keyboard if nobody did.
+            (Ok(JoinButton::Play), _) => start = true,
+            (Ok(JoinButton::Back), _) => back = true,
+            (Err(_), _) => {}
         }
-        AppState::HowToPlay => {
-            if keys.just_pressed(KeyCode::Enter) {
-                next_state.set(AppState::Playing);
+    }
+
+    // Back leaves, or returns if not joined.
+    for device in actions.input_devices() {
+        if actions.device_just_pressed(device, Action::Back) {
+            if players.has_joined(device) {
+                players.leave(device);
+            } else {
+                back = true;
             }
         }
-        AppState::Playing => {}
+    }
+
+    if start {
+        next_state.set(AppState::Playing);
+    } else if back {
+        current_level.start_index = None;
+        next_state.set(AppState::StartMenu);
     }
 }
 
-fn clear_start(
+fn update_join_slots(
+    players: Res<Players>,
+    names: Query<&Name>,
+    mut slot_q: Query<(&JoinSlot, &mut Text, &mut TextColor)>,
+) {
+    for (slot, mut text, mut color) in slot_q.iter_mut() {
+        let (slot_text, slot_color) = match players.devices.get(slot.0) {
+            Some(InputDevice::Keyboard) => ("Keyboard".to_string(), dog::player_color(slot.0)),
+            Some(InputDevice::Gamepad(entity)) => {
+                let name = names.get(*entity).map(Name::as_str).unwrap_or("Gamepad");
+                (name.to_string(), dog::player_color(slot.0))
+            }
+            None => ("Press to join".to_string(), DISABLED_COLOR),
+        };
+        let slot_text = format!("P{}: {}", slot.0 + 1, slot_text);
+        if text.0 != slot_text {
+            text.0 = slot_text;
+        }
+        if color.0 != slot_color {
+            color.0 = slot_color;
+        }
+    }
+}
+
+fn clear_join(
     mut commands: Commands,
     root_q: Query<Entity, With<MenuRoot>>,
 ) {
@@ -198,22 +479,16 @@ fn show_credits(
         },
     ));
 
-    let return_text = (
-        Name::new("ReturnText"),
-        Blink::from_seconds(0.5, true),
-        Text("Press Tab to return!".into()),
-        TextColor(Color::BLACK),
-        TextFont {
-            font: assets.font.clone(),
-            font_size: 13.0,
-            ..default()
-        },
+    let return_buttons = (
         Node {
             position_type: PositionType::Absolute,
             right: Val::Px(4.0),
             bottom: Val::Px(6.0),
             ..default()
         },
+        children![
+            menu_button("Back", AppState::StartMenu, assets.font.clone()),
+        ],
     );
     commands.spawn((
         MenuRoot,
@@ -228,7 +503,7 @@ fn show_credits(
             credits_name_text("Justin Hamilton", vec2(105.0, 98.0), assets.font.clone()),
             credits_name_text("Gabriel Martinez", vec2(105.0, 132.0), assets.font.clone()),
             credits_name_text("Music by Thaminda Edirisooriya", vec2(105.0, 163.0), assets.font.clone()),
-            return_text,
+            return_buttons,
         ],
     ));
 }
@@ -353,20 +628,17 @@ fn show_how_to_play(
             ..default()
         },
         children![(
-            Blink::from_seconds(0.5, true),
-            Text("Press Enter to play!".into()),
-            TextColor(Color::BLACK),
-            TextFont {
-                font: assets.font.clone(),
-                font_size: 13.0,
-                ..default()
-            },
             Node {
                 position_type: PositionType::Absolute,
                 right: Val::Px(4.0),
                 bottom: Val::Px(6.0),
+                column_gap: Val::Px(4.0),
                 ..default()
             },
+            children![
+                menu_button("Play", AppState::Join, assets.font.clone()),
+                menu_button("Back", AppState::StartMenu, assets.font.clone()),
+            ],
         )],
     ));
 }
@@ -0,0 +1,201 @@
+use bevy::prelude::*;
+use bevy::window::{Monitor, PrimaryMonitor, PrimaryWindow};
+use bevy_egui::input::egui_wants_any_input;
+
+use crate::{
+    AppState,
+    assets::GameAssets,
+    audio::AudioSettings,
+    input::{Action, ActionState},
+    window::{self, WindowState},
+};
+
+use super::buttons::{button, set_button_text, ButtonPressed, Focused, MenuTarget};
+
+const VOLUME_STEP: f32 = 0.1;
+
+/// Settings that can be adjusted with left and right.
+#[derive(Clone, Copy)]
+enum OptionsRow {
+    MasterVolume,
+    MusicVolume,
+    SfxVolume,
+    WindowScale,
+    Fullscreen,
+}
+
+impl OptionsRow {
+    const ALL: [OptionsRow; 5] = [
+        OptionsRow::MasterVolume,
+        OptionsRow::MusicVolume,
+        OptionsRow::SfxVolume,
+        OptionsRow::WindowScale,
+        OptionsRow::Fullscreen,
+    ];
+
+    fn text(&self, audio_settings: &AudioSettings, window_state: &WindowState) -> String {
+        match self {
+            OptionsRow::MasterVolume => format!("Master Volume: {}", volume_text(audio_settings.master)),
+            OptionsRow::MusicVolume => format!("Music Volume: {}", volume_text(audio_settings.music)),
+            OptionsRow::SfxVolume => format!("Sound Volume: {}", volume_text(audio_settings.sfx)),
+            OptionsRow::WindowScale if window_state.fullscreen => "Window Scale: Fullscreen".into(),
+            OptionsRow::WindowScale => format!("Window Scale: {}x", window_state.scale),
+            OptionsRow::Fullscreen => format!("Fullscreen: {}", if window_state.fullscreen { "On" } else { "Off" }),
+        }
+    }
+}
+
+pub struct OptionsPlugin;
+
+impl Plugin for OptionsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnEnter(AppState::Options), show_options)
+            .add_systems(OnExit(AppState::Options), clear_options)
+            .add_systems(Update, (
+                handle_options_input.run_if(not(egui_wants_any_input)),
+                update_options_menu,
+            ).chain().run_if(in_state(AppState::Options)));
+    }
+}
+
+#[derive(Component)]
+struct OptionsMenu;
+
+#[derive(Component)]
+struct OptionsEntry(OptionsRow);
+
+fn show_options(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+) {
+    let text_font = TextFont {
+        font: assets.font.clone(),
+        font_size: 11.0,
+        ..default()
+    };
+    commands.spawn((
+        Name::new("OptionsMenu"),
+        OptionsMenu,
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::FlexStart,
+            padding: UiRect::all(Val::Px(8.0)),
+            row_gap: Val::Px(2.0),
+            ..default()
+        },
+        BackgroundColor(Color::srgb(0.1, 0.1, 0.15)),
+    )).with_children(|parent| {
+        parent.spawn((
+            Text("Options".into()),
+            TextColor(Color::WHITE),
+            TextFont {
+                font_size: 14.0,
+                ..text_font.clone()
+            },
+        ));
+
+        for row in OptionsRow::ALL {
+            parent.spawn((OptionsEntry(row), button("", text_font.clone())));
+        }
+        parent.spawn((MenuTarget(AppState::StartMenu), button("Back", text_font.clone())));
+
+        parent.spawn((
+            Text("Left/Right to adjust, Back to return".into()),
+            TextColor(Color::WHITE),
+            text_font.clone(),
+            Node {
+                position_type: PositionType::Absolute,
+                right: Val::Px(4.0),
+                bottom: Val::Px(6.0),
+                ..default()
+            },
+        ));
+    });
+}
+
+fn handle_options_input(
+    actions: Res<ActionState>,
+    mut presses: MessageReader<ButtonPressed>,
+    entry_q: Query<(&OptionsEntry, Has<Focused>)>,
+    mut audio_settings: ResMut<AudioSettings>,
+    window_state: Res<WindowState>,
+    mut window_q: Query<&mut Window, With<PrimaryWindow>>,
+    monitor_q: Query<&Monitor, With<PrimaryMonitor>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if actions.just_pressed(Action::Back) {
+        next_state.set(AppState::StartMenu);
+        return;
+    }
+
+    // Pressing a setting steps it up, while left and right step the focused one either way.
+    let mut adjustments: Vec<(OptionsRow, f32)> = presses.read()
+        .filter_map(|pressed| entry_q.get(pressed.entity).ok())
+        .map(|(entry, _)| (entry.0, 1.0))
+        .collect();
+    let dir = if actions.just_pressed(Action::MoveLeft) {
+        -1.0
+    } else if actions.just_pressed(Action::MoveRight) {
+        1.0
+    } else {
+        0.0
+    };
+    if dir != 0.0 && let Some((entry, _)) = entry_q.iter().find(|(_, focused)| *focused) {
+        adjustments.push((entry.0, dir));
+    }
+
+    for (row, dir) in adjustments {
+        match row {
+            OptionsRow::MasterVolume => audio_settings.master = step_volume(audio_settings.master, dir),
+            OptionsRow::MusicVolume => audio_settings.music = step_volume(audio_settings.music, dir),
+            OptionsRow::SfxVolume => audio_settings.sfx = step_volume(audio_settings.sfx, dir),
+            OptionsRow::WindowScale => {
+                if let Ok(mut window) = window_q.single_mut() {
+                    window::step_window_scale(&mut window, &window_state, dir as i8, monitor_q.single().ok());
+                }
+            }
+            OptionsRow::Fullscreen => {
+                if let Ok(mut window) = window_q.single_mut() {
+                    window::toggle_fullscreen(&mut window, &window_state);
+                }
+            }
+        }
+    }
+}
+
+/// Round to the nearest step so repeated adjustments don't drift.
+fn step_volume(volume: f32, dir: f32) -> f32 {
+    ((volume / VOLUME_STEP).round() + dir).clamp(0.0, 1.0 / VOLUME_STEP) * VOLUME_STEP
+}
+
+fn update_options_menu(
+    audio_settings: Res<AudioSettings>,
+    window_state: Res<WindowState>,
+    entry_q: Query<(Ref<OptionsEntry>, &Children)>,
+    mut text_q: Query<&mut Text>,
+) {
+    for (entry, children) in entry_q.iter() {
+        if !entry.is_added() && !audio_settings.is_changed() && !window_state.is_changed() {
+            continue;
+        }
+        set_button_text(children, &mut text_q, entry.0.text(&audio_settings, &window_state));
+    }
+}
+
+/// Draw the volume as a bar of ten segments.
+fn volume_text(volume: f32) -> String {
+    let filled = (volume.clamp(0.0, 1.0) * 10.0).round() as usize;
+    format!("{}{} {:>3}%", "|".repeat(filled), ".".repeat(10 - filled), (volume * 100.0).round())
+}
+
+fn clear_options(
+    mut commands: Commands,
+    menu_q: Query<Entity, With<OptionsMenu>>,
+) {
+    for entity in menu_q.iter() {
+        commands.entity(entity).despawn();
+    }
+}
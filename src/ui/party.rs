@@ -6,6 +6,7 @@ use crate::{
     assets::GameAssets,
     cats::{self, CatKind},
     game::GameState,
+    rng::GameRng,
 };
 
 const NUM_CATS: u32 = 60;
@@ -33,7 +34,10 @@ struct PartyDog;
 fn spawn_party(
     mut commands: Commands,
     assets: Res<GameAssets>,
+    mut rng: ResMut<GameRng>,
 ) {
+    let rng = &mut rng.effects;
+
     // Spawn doggo!
     commands.spawn((
         Name::new("PartyDog"),
@@ -50,16 +54,12 @@ fn spawn_party(
 
     // Spawn cats!
     for i in 0..NUM_CATS {
-        let kind = CatKind::random();
-        let angle = 360.0 * fastrand::f32();
-        let x = (WORLD_SIZE.x as f32 * fastrand::f32()) - WORLD_SIZE.x as f32 / 2.0;
-        let y = ((WORLD_SIZE.y + 80) as f32 * fastrand::f32()) + WORLD_SIZE.y as f32 / 2.0;
+        let kind = CatKind::random(rng);
+        let angle = 360.0 * rng.f32();
+        let x = (WORLD_SIZE.x as f32 * rng.f32()) - WORLD_SIZE.x as f32 / 2.0;
+        let y = ((WORLD_SIZE.y + 80) as f32 * rng.f32()) + WORLD_SIZE.y as f32 / 2.0;
         let z = 100.0 + i as f32;
-        let aseprite = match kind {
-            CatKind::Basic => &assets.basic_cat,
-            CatKind::Kitten => &assets.kitten,
-            CatKind::Chonk => &assets.fat_cat,
-        }.clone();
+        let aseprite = kind.aseprite(&assets);
 
         commands.spawn((
             Name::new("PartyCat!"),
@@ -70,8 +70,8 @@ fn spawn_party(
                 .with_rotation(Quat::from_rotation_z(angle.to_radians()))
                 .with_scale(Vec3::new(1.5, 1.5, 1.0)),
             Sprite {
-                color: cats::random_cat_color(),
-                flip_x: fastrand::bool(),
+                color: cats::random_cat_color(rng),
+                flip_x: rng.bool(),
                 ..default()
             },
             AseAnimation {
@@ -102,8 +102,10 @@ fn destroy_party(
 
 fn update_cats(
     time: Res<Time>,
+    mut rng: ResMut<GameRng>,
     mut cat_q: Query<(&PartyCat, &mut Transform)>,
 ) {
+    let rng = &mut rng.effects;
     let dt = time.delta_secs();
     for (cat, mut transform) in cat_q.iter_mut() {
         // Update rotation
@@ -119,8 +121,8 @@ fn update_cats(
 
         // Wrap around once hit the bottom of the screen.
         if transform.translation.y < -(WORLD_SIZE.y as f32 / 2.0) - 50.0 {
-            let angle = 360.0 * fastrand::f32();
-            let x = (WORLD_SIZE.x as f32 * fastrand::f32()) - WORLD_SIZE.x as f32 / 2.0;
+            let angle = 360.0 * rng.f32();
+            let x = (WORLD_SIZE.x as f32 * rng.f32()) - WORLD_SIZE.x as f32 / 2.0;
             let y = (WORLD_SIZE.y as f32 / 2.0) + 50.0;
             transform.rotation = Quat::from_rotation_z(angle.to_radians());
             transform.translation.x = x;
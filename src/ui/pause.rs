@@ -0,0 +1,127 @@
+use bevy::prelude::*;
+use bevy_egui::input::egui_wants_any_input;
+
+use crate::{
+    AppState,
+    assets::GameAssets,
+    autopilot::Demo,
+    game::PauseState,
+    input::{Action, ActionState},
+    level::RespawnLevel,
+};
+
+use super::buttons::{button, ButtonPressed};
+
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnEnter(PauseState::Paused), show_pause_menu)
+            .add_systems(OnExit(PauseState::Paused), clear_pause_menu)
+            .add_systems(Update, (
+                toggle_pause.run_if(in_state(PauseState::Running).and(not(resource_exists::<Demo>))),
+                handle_pause_input.run_if(in_state(PauseState::Paused)),
+            ).run_if(not(egui_wants_any_input)));
+    }
+}
+
+#[derive(Component)]
+struct PauseMenu;
+
+#[derive(Clone, Copy, Component)]
+enum PauseItem {
+    Resume,
+    Restart,
+    Quit,
+}
+
+fn toggle_pause(
+    actions: Res<ActionState>,
+    mut next_state: ResMut<NextState<PauseState>>,
+) {
+    if actions.just_pressed(Action::Pause) {
+        next_state.set(PauseState::Paused);
+    }
+}
+
+fn show_pause_menu(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+) {
+    let text_font = TextFont {
+        font: assets.font.clone(),
+        font_size: 13.0,
+        ..default()
+    };
+    commands.spawn((
+        Name::new("PauseMenu"),
+        PauseMenu,
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            row_gap: Val::Px(4.0),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+        GlobalZIndex(10),
+    )).with_children(|parent| {
+        parent.spawn((
+            Text("Paused".into()),
+            TextColor(Color::WHITE),
+            TextFont {
+                font_size: 20.0,
+                ..text_font.clone()
+            },
+            TextShadow {
+                offset: Vec2::splat(0.8),
+                color: Color::BLACK,
+            },
+            Node {
+                margin: UiRect::bottom(Val::Px(6.0)),
+                ..default()
+            },
+        ));
+        parent.spawn((PauseItem::Resume, button("Resume", text_font.clone())));
+        parent.spawn((PauseItem::Restart, button("Restart Level", text_font.clone())));
+        parent.spawn((PauseItem::Quit, button("Quit to Title", text_font.clone())));
+    });
+}
+
+fn handle_pause_input(
+    actions: Res<ActionState>,
+    mut presses: MessageReader<ButtonPressed>,
+    item_q: Query<&PauseItem>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+    mut respawn: MessageWriter<RespawnLevel>,
+) {
+    for pressed in presses.read() {
+        match item_q.get(pressed.entity) {
+            Ok(PauseItem::Resume) => next_pause_state.set(PauseState::Running),
+            Ok(PauseItem::Restart) => {
+                respawn.write(RespawnLevel);
+                next_pause_state.set(PauseState::Running);
+            }
+            Ok(PauseItem::Quit) => next_app_state.set(AppState::StartMenu),
+            Err(_) => {}
+        }
+    }
+
+    if actions.just_pressed(Action::Back) || actions.just_pressed(Action::Pause) {
+        next_pause_state.set(PauseState::Running);
+    }
+}
+
+fn clear_pause_menu(
+    mut commands: Commands,
+    menu_q: Query<Entity, With<PauseMenu>>,
+) {
+    for entity in menu_q.iter() {
+        commands.entity(entity).despawn();
+    }
+}
@@ -1,16 +1,15 @@
-use std::fs;
-use std::path::Path;
-
 use bevy::prelude::*;
-use bevy::app::AppExit;
 use bevy::diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin};
-use bevy::window::PrimaryWindow;
+use bevy::window::{Monitor, MonitorSelection, PrimaryMonitor, PrimaryWindow, WindowMode};
 use serde::{Deserialize, Serialize};
 
-use crate::{DEFAULT_SCALE, SCREEN_SIZE};
+use crate::{
+    DEFAULT_SCALE, SCREEN_SIZE,
+    input::{Action, ActionState},
+};
 
 pub const WINDOW_TITLE: &str = "Cat Chaser";
-const WINDOW_STATE_FILENAME: &str = "window_state.ron";
+const MAX_SCALE: u8 = 4;
 
 #[derive(Clone, Debug, Deserialize, Serialize, Resource)]
 pub struct WindowState {
@@ -18,6 +17,8 @@ pub struct WindowState {
     pub position: WindowPosition,
     #[serde(default)]
     pub scale: u8,
+    #[serde(default)]
+    pub fullscreen: bool,
 }
 
 impl Default for WindowState {
@@ -25,22 +26,11 @@ impl Default for WindowState {
         Self {
             position: WindowPosition::Automatic,
             scale: DEFAULT_SCALE,
+            fullscreen: false,
         }
     }
 }
 
-pub fn load_window_state() -> WindowState {
-    if Path::new(WINDOW_STATE_FILENAME).is_file() {
-        // TODO: Log errors if these fail and return default.
-        let window_state_str = fs::read_to_string(WINDOW_STATE_FILENAME)
-            .expect("Could not read window state file");
-        ron::from_str(&window_state_str)
-            .expect("Could not deserialize window state")
-    } else {
-        default()
-    }
-}
-
 #[derive(Resource)]
 struct LogFpsTimer(Timer);
 
@@ -66,12 +56,12 @@ impl Plugin for WindowPlugin {
     fn build(&self, app: &mut App) {
         app
             .insert_resource(self.saved_window_state.clone())
+            .add_systems(Update, handle_window_hotkeys)
             .add_systems(PostUpdate, update_window_state);
         #[cfg(not(target_arch = "wasm32"))]
         {
             app
-                .add_systems(Startup, window_icon::set_window_icon)
-                .add_systems(Last, save_window_state_on_exit.run_if(on_message::<AppExit>));
+                .add_systems(Startup, window_icon::set_window_icon);
 
             app
                 .insert_resource(LogFpsTimer::default())
@@ -121,13 +111,66 @@ pub fn update_window_state(
     window_q: Query<&Window, (With<PrimaryWindow>, Changed<Window>)>,
 ) {
     if let Ok(window) = window_q.single() {
-        window_state.position = window.position;
+        window_state.fullscreen = window.mode != WindowMode::Windowed;
+        if !window_state.fullscreen {
+            window_state.position = window.position;
+        }
         let scale_x = window.physical_width() / SCREEN_SIZE.x;
         let scale_y = window.physical_height() / SCREEN_SIZE.y;
         window_state.scale = scale_x.min(scale_y).max(1) as u8;
     }
 }
 
+/// The largest scale that fits on the monitor, or `MAX_SCALE` if we don't know the monitor.
+pub fn max_scale(monitor: Option<&Monitor>) -> u8 {
+    monitor.map_or(MAX_SCALE, |monitor| {
+        let fit = monitor.physical_size() / SCREEN_SIZE;
+        fit.x.min(fit.y).clamp(1, MAX_SCALE as u32) as u8
+    })
+}
+
+/// Resize the window to a larger or smaller multiple of the screen size.
+pub fn step_window_scale(window: &mut Window, window_state: &WindowState, step: i8, monitor: Option<&Monitor>) {
+    // The window fills the monitor in fullscreen, so there's nothing to scale.
+    if window_state.fullscreen {
+        return;
+    }
+    let scale = window_state.scale.saturating_add_signed(step).clamp(1, max_scale(monitor));
+    let size = SCREEN_SIZE * scale as u32;
+    window.resolution.set_physical_resolution(size.x, size.y);
+}
+
+pub fn toggle_fullscreen(window: &mut Window, window_state: &WindowState) {
+    window.mode = window_mode(!window_state.fullscreen);
+}
+
+pub fn window_mode(fullscreen: bool) -> WindowMode {
+    if fullscreen {
+        WindowMode::BorderlessFullscreen(MonitorSelection::Current)
+    } else {
+        WindowMode::Windowed
+    }
+}
+
+fn handle_window_hotkeys(
+    actions: Res<ActionState>,
+    window_state: Res<WindowState>,
+    mut window_q: Query<&mut Window, With<PrimaryWindow>>,
+    monitor_q: Query<&Monitor, With<PrimaryMonitor>>,
+) {
+    let Ok(mut window) = window_q.single_mut() else {
+        return;
+    };
+    if actions.just_pressed(Action::ScaleUp) {
+        step_window_scale(&mut window, &window_state, 1, monitor_q.single().ok());
+    } else if actions.just_pressed(Action::ScaleDown) {
+        step_window_scale(&mut window, &window_state, -1, monitor_q.single().ok());
+    }
+    if actions.just_pressed(Action::Fullscreen) {
+        toggle_fullscreen(&mut window, &window_state);
+    }
+}
+
 fn log_fps_in_window_title(
     mut window_q: Query<&mut Window, With<PrimaryWindow>>,
     mut log_fps_timer: ResMut<LogFpsTimer>,
@@ -147,15 +190,3 @@ fn log_fps_in_window_title(
         }
     }
 }
-
-fn save_window_state_on_exit(
-    window_state: Res<WindowState>,
-) {
-    info!("Saving window state");
-
-    let pretty_config = ron::ser::PrettyConfig::default();
-    let state_str = ron::ser::to_string_pretty(&*window_state, pretty_config)
-        .expect("Could not serialize window state");
-    fs::write(WINDOW_STATE_FILENAME, state_str)
-        .expect("Could not write window state to file");
-}